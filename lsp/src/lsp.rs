@@ -4,6 +4,7 @@ use std::sync::Mutex;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
+use zhttp_shared::DYNAMIC_VARIABLES;
 
 struct HttpLsp {
     client: Client,
@@ -40,7 +41,7 @@ impl LanguageServer for HttpLsp {
                     TextDocumentSyncKind::FULL,
                 )),
                 completion_provider: Some(CompletionOptions {
-                    trigger_characters: Some(vec!["\n".into(), " ".into()]),
+                    trigger_characters: Some(vec!["\n".into(), " ".into(), "{".into(), "$".into()]),
                     ..Default::default()
                 }),
                 ..Default::default()
@@ -89,14 +90,12 @@ impl LanguageServer for HttpLsp {
         let character = params.text_document_position.position.character;
 
         let line_text = self.get_line(&uri, line);
+        let trigger = params
+            .context
+            .as_ref()
+            .and_then(|context| context.trigger_character.as_deref());
 
-        let items = if character == 0 || is_method_position(&line_text) {
-            method_completions()
-        } else {
-            header_completions()
-        };
-
-        Ok(Some(CompletionResponse::Array(items)))
+        Ok(completions(&line_text, line as u32, character, trigger).map(CompletionResponse::Array))
     }
 }
 
@@ -110,6 +109,32 @@ impl HttpLsp {
     }
 }
 
+/// The completions at `character` of `line_text`. `{` and `$` only trigger
+/// variable completions, so typing them elsewhere, as in a JSON body, doesn't
+/// offer headers.
+fn completions(
+    line_text: &str,
+    line: u32,
+    character: u32,
+    trigger: Option<&str>,
+) -> Option<Vec<CompletionItem>> {
+    if let Some(start) = variable_start(line_text, character as usize) {
+        let range = Range::new(
+            Position::new(line, start as u32),
+            Position::new(line, character),
+        );
+        return Some(dynamic_variable_completions(range));
+    }
+    if matches!(trigger, Some("{" | "$")) {
+        return None;
+    }
+    if character == 0 || is_method_position(line_text) {
+        Some(method_completions())
+    } else {
+        Some(header_completions())
+    }
+}
+
 fn is_method_position(line_text: &str) -> bool {
     let trimmed = line_text.trim();
     if trimmed.is_empty() {
@@ -119,6 +144,28 @@ fn is_method_position(line_text: &str) -> bool {
     HTTP_METHODS.iter().any(|m| upper.starts_with(m))
 }
 
+/// Returns the position just after an unclosed `{{` before the cursor, i.e.
+/// where the variable name being typed starts. Positions count UTF-16 code
+/// units, as LSP does.
+fn variable_start(line_text: &str, character: usize) -> Option<usize> {
+    let prefix = utf16_prefix(line_text, character);
+    let open = prefix.rfind("{{")?;
+    let closed = prefix[open..].contains("}}");
+    (!closed).then(|| prefix[..open + 2].encode_utf16().count())
+}
+
+/// The part of `line` before the UTF-16 offset `character`.
+fn utf16_prefix(line: &str, character: usize) -> &str {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character {
+            return &line[..i];
+        }
+        units += c.len_utf16();
+    }
+    line
+}
+
 fn method_completions() -> Vec<CompletionItem> {
     HTTP_METHODS
         .iter()
@@ -156,6 +203,24 @@ fn header_completions() -> Vec<CompletionItem> {
         .collect()
 }
 
+fn dynamic_variable_completions(range: Range) -> Vec<CompletionItem> {
+    DYNAMIC_VARIABLES
+        .iter()
+        .enumerate()
+        .map(|(i, (name, description))| CompletionItem {
+            label: name.to_string(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(description.to_string()),
+            sort_text: Some(format!("{:02}", i)),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                range,
+                name.to_string(),
+            ))),
+            ..Default::default()
+        })
+        .collect()
+}
+
 pub fn start() {
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -172,3 +237,34 @@ pub fn start() {
             Server::new(stdin, stdout, socket).serve(service).await;
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(items: Option<Vec<CompletionItem>>) -> Option<Vec<String>> {
+        items.map(|items| items.into_iter().map(|item| item.label).collect())
+    }
+
+    #[test]
+    fn braces_and_dollars_only_complete_variables() {
+        let variables = labels(completions("GET {{$", 0, 7, Some("$"))).unwrap();
+        assert_eq!(variables[0], "$uuid");
+        assert_eq!(completions("{\"id\": {", 3, 9, Some("{")), None);
+        assert_eq!(completions("  \"price\": \"$", 3, 13, Some("$")), None);
+
+        let headers = labels(completions("Accept", 1, 6, Some(" "))).unwrap();
+        assert_eq!(headers[0], "Accept");
+        let methods = labels(completions("", 0, 0, Some("\n"))).unwrap();
+        assert_eq!(methods[0], "GET");
+    }
+
+    #[test]
+    fn variable_start_counts_utf16_units() {
+        // The emoji is two UTF-16 code units but one char.
+        let line = "GET /\u{1f600}/{{$uu";
+        assert_eq!(variable_start(line, 13), Some(10));
+        assert_eq!(variable_start(line, 8), None);
+        assert_eq!(variable_start("{{a}} x", 7), None);
+    }
+}
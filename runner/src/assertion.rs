@@ -0,0 +1,366 @@
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::error::RunError;
+use crate::exec::Response;
+use crate::script::TestResult;
+
+/// A parsed `?? subject operator value` line.
+#[derive(Debug, PartialEq)]
+pub struct Assertion {
+    text: String,
+    subject: Subject,
+    op: Operator,
+    expected: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Subject {
+    Status,
+    Header(String),
+    Body,
+    JsonPath(String),
+    Duration,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    NotContains,
+    Exists,
+}
+
+const OPERATORS: &[(&str, Operator)] = &[
+    ("==", Operator::Eq),
+    ("!=", Operator::Ne),
+    ("<=", Operator::Le),
+    (">=", Operator::Ge),
+    ("<", Operator::Lt),
+    (">", Operator::Gt),
+    ("contains", Operator::Contains),
+    ("!contains", Operator::NotContains),
+    ("exists", Operator::Exists),
+];
+
+pub fn parse_all(lines: &[String]) -> Result<Vec<Assertion>, RunError> {
+    lines.iter().map(|line| parse(line)).collect()
+}
+
+/// Parses the text after `??`, e.g. `status == 200`,
+/// `header Content-Type contains json`, `jsonpath $.items.length > 0` or
+/// `duration < 500ms`.
+fn parse(text: &str) -> Result<Assertion, RunError> {
+    let invalid = |reason: &str| RunError::InvalidAssertion(text.to_string(), reason.to_string());
+
+    let (keyword, rest) = split_token(text);
+    let (subject, rest) = match keyword {
+        "status" => (Subject::Status, rest),
+        "body" => (Subject::Body, rest),
+        "duration" => (Subject::Duration, rest),
+        "header" | "jsonpath" => {
+            let (arg, rest) = split_token(rest);
+            if arg.is_empty() {
+                return Err(invalid(&format!("{} needs a name", keyword)));
+            }
+            if keyword == "header" {
+                (Subject::Header(arg.to_string()), rest)
+            } else if arg.starts_with('$') {
+                (Subject::JsonPath(arg.to_string()), rest)
+            } else {
+                return Err(invalid("JSON paths start with $"));
+            }
+        }
+        _ => {
+            return Err(invalid(
+                "expected status, header, body, jsonpath or duration",
+            ))
+        }
+    };
+
+    let (op_text, expected) = split_token(rest);
+    let op = OPERATORS
+        .iter()
+        .find(|(name, _)| *name == op_text)
+        .map(|(_, op)| *op)
+        .ok_or_else(|| invalid(&format!("unknown operator '{}'", op_text)))?;
+    let expected = expected.trim();
+
+    match op {
+        Operator::Exists if !expected.is_empty() => {
+            return Err(invalid("exists takes no value"));
+        }
+        Operator::Exists => {}
+        _ if expected.is_empty() => return Err(invalid("missing expected value")),
+        _ => {}
+    }
+    let expected = unquote(expected).to_string();
+    if subject == Subject::Duration && op != Operator::Exists && parse_millis(&expected).is_none() {
+        return Err(invalid("durations are written like 500ms or 2s"));
+    }
+
+    Ok(Assertion {
+        text: text.to_string(),
+        subject,
+        op,
+        expected,
+    })
+}
+
+fn split_token(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    text.split_once(char::is_whitespace).unwrap_or((text, ""))
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_millis(value: &str) -> Option<f64> {
+    if let Some(ms) = value.strip_suffix("ms") {
+        ms.trim().parse().ok()
+    } else if let Some(s) = value.strip_suffix('s') {
+        s.trim().parse::<f64>().ok().map(|s| s * 1000.0)
+    } else {
+        value.parse().ok()
+    }
+}
+
+fn format_millis(elapsed: Duration) -> String {
+    format!("{}ms", elapsed.as_millis())
+}
+
+/// Checks each assertion against the response, reporting them like
+/// `client.test` results.
+pub fn evaluate(assertions: &[Assertion], response: &Response) -> Vec<TestResult> {
+    let json: Option<Value> = serde_json::from_slice(&response.body).ok();
+
+    assertions
+        .iter()
+        .map(|assertion| {
+            let actual = match &assertion.subject {
+                Subject::Status => Some(response.status.to_string()),
+                Subject::Header(name) => response.header(name).map(str::to_string),
+                Subject::Body => Some(response.text()),
+                Subject::JsonPath(path) => {
+                    json.as_ref()
+                        .and_then(|json| select(json, path))
+                        .map(|value| match value {
+                            Value::String(s) => s,
+                            other => other.to_string(),
+                        })
+                }
+                Subject::Duration => Some(format_millis(response.elapsed)),
+            };
+            let passed = check(assertion, actual.as_deref());
+            TestResult {
+                name: assertion.text.clone(),
+                passed,
+                message: (!passed).then(|| match &actual {
+                    Some(actual) => format!("actual: {}", actual),
+                    None => "no such value in the response".to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+fn check(assertion: &Assertion, actual: Option<&str>) -> bool {
+    let Some(actual) = actual else {
+        return false;
+    };
+    let expected = assertion.expected.as_str();
+    let numbers = if assertion.subject == Subject::Duration {
+        parse_millis(actual).zip(parse_millis(expected))
+    } else {
+        actual.parse::<f64>().ok().zip(expected.parse::<f64>().ok())
+    };
+
+    match assertion.op {
+        Operator::Exists => true,
+        Operator::Contains => actual.contains(expected),
+        Operator::NotContains => !actual.contains(expected),
+        Operator::Eq => match numbers {
+            Some((a, e)) => a == e,
+            None => actual == expected,
+        },
+        Operator::Ne => match numbers {
+            Some((a, e)) => a != e,
+            None => actual != expected,
+        },
+        op => {
+            let Some((a, e)) = numbers else {
+                return false;
+            };
+            match op {
+                Operator::Lt => a < e,
+                Operator::Le => a <= e,
+                Operator::Gt => a > e,
+                _ => a >= e,
+            }
+        }
+    }
+}
+
+/// Follows a path such as `$.items[0].name` through `json`. A trailing
+/// `.length` on an array, object or string yields its size unless the
+/// object has a `length` key.
+fn select(json: &Value, path: &str) -> Option<Value> {
+    let mut current = json.clone();
+    let mut rest = path.strip_prefix('$')?;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (index, after) = after.split_once(']')?;
+            current = match unquote(index.trim()) {
+                key if index.trim().starts_with('"') => current.get(key)?.clone(),
+                index => current.get(index.parse::<usize>().ok()?)?.clone(),
+            };
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let key = &after[..end];
+            current = match (&current, current.get(key)) {
+                (_, Some(value)) => value.clone(),
+                (Value::Array(items), None) if key == "length" => items.len().into(),
+                (Value::Object(map), None) if key == "length" => map.len().into(),
+                (Value::String(s), None) if key == "length" => s.chars().count().into(),
+                _ => return None,
+            };
+            rest = &after[end..];
+        } else {
+            return None;
+        }
+    }
+
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str, millis: u64) -> Response {
+        Response {
+            status,
+            status_text: String::new(),
+            http_version: "HTTP/1.1".to_string(),
+            headers: headers
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+            elapsed: Duration::from_millis(millis),
+            wire_size: None,
+            timings: Default::default(),
+            redirects: Vec::new(),
+            proxy: None,
+        }
+    }
+
+    fn run(lines: &[&str], response: &Response) -> Vec<bool> {
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        evaluate(&parse_all(&lines).unwrap(), response)
+            .iter()
+            .map(|r| r.passed)
+            .collect()
+    }
+
+    #[test]
+    fn parse_recognizes_subjects_and_operators() {
+        let a = parse("header Content-Type contains \"json\"").unwrap();
+        assert_eq!(a.subject, Subject::Header("Content-Type".to_string()));
+        assert_eq!(a.op, Operator::Contains);
+        assert_eq!(a.expected, "json");
+
+        let a = parse("jsonpath $.items exists").unwrap();
+        assert_eq!(a.subject, Subject::JsonPath("$.items".to_string()));
+        assert_eq!(a.op, Operator::Exists);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_assertions() {
+        for text in [
+            "state == 200",
+            "status ~ 200",
+            "status ==",
+            "header",
+            "jsonpath items > 0",
+            "duration < soon",
+            "body exists now",
+        ] {
+            assert!(
+                matches!(parse(text), Err(RunError::InvalidAssertion(..))),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_status_header_body_and_duration() {
+        let resp = response(
+            201,
+            &[("Content-Type", "application/json")],
+            "{\"ok\": true}",
+            120,
+        );
+        assert_eq!(
+            run(
+                &[
+                    "status == 201",
+                    "status >= 400",
+                    "header content-type contains json",
+                    "header X-Missing exists",
+                    "body contains \"ok\"",
+                    "duration < 500ms",
+                    "duration > 0.1s",
+                ],
+                &resp
+            ),
+            vec![true, false, true, false, true, true, true]
+        );
+    }
+
+    #[test]
+    fn evaluate_jsonpath() {
+        let resp = response(
+            200,
+            &[],
+            r#"{"items": [{"name": "a"}, {"name": "b"}], "total": 2, "tag": "x"}"#,
+            0,
+        );
+        assert_eq!(
+            run(
+                &[
+                    "jsonpath $.items.length > 0",
+                    "jsonpath $.items[1].name == b",
+                    "jsonpath $[\"total\"] == 2.0",
+                    "jsonpath $.tag != x",
+                    "jsonpath $.missing exists",
+                    "jsonpath $.items[0].name != \"\"",
+                ],
+                &resp
+            ),
+            vec![true, true, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn failed_assertions_report_actual_value() {
+        let resp = response(404, &[], "", 0);
+        let lines = vec!["status == 200".to_string()];
+        let results = evaluate(&parse_all(&lines).unwrap(), &resp);
+        assert_eq!(results[0].name, "status == 200");
+        assert_eq!(results[0].message.as_deref(), Some("actual: 404"));
+    }
+}
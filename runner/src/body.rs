@@ -0,0 +1,211 @@
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use crate::error::RunError;
+use crate::multipart;
+use crate::parse::RequestBlock;
+use crate::vars::{substitute, Scope};
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Bytes(Vec<u8>),
+    File(PathBuf),
+}
+
+/// A request body assembled from inline text and `< file` includes. Plain
+/// includes are streamed from disk when the request is sent.
+#[derive(Debug, Clone)]
+pub struct Body {
+    segments: Vec<Segment>,
+    len: u64,
+}
+
+enum Include<'a> {
+    Raw(&'a str),
+    Substituted(&'a str),
+}
+
+fn parse_include(line: &str) -> Option<Include<'_>> {
+    let trimmed = line.trim();
+    if let Some(path) = trimmed.strip_prefix("<@") {
+        return Some(Include::Substituted(path.trim()));
+    }
+    let path = trimmed.strip_prefix('<')?;
+    if !path.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(Include::Raw(path.trim()))
+}
+
+impl Body {
+    /// Builds the body for a parsed request, framing it as multipart when the
+    /// request declares a `multipart/form-data` content type.
+    pub fn from_request(
+        req: &RequestBlock,
+        base_dir: &Path,
+        scope: &Scope,
+    ) -> Result<Option<Body>, RunError> {
+        let Some(text) = req.body.as_deref() else {
+            return Ok(None);
+        };
+        let content_type = req
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+            .map(|(_, value)| value.as_str());
+
+        match content_type.and_then(multipart::boundary) {
+            Some(boundary) => multipart::assemble(text, &boundary?, base_dir, scope).map(Some),
+            None => Body::assemble(text, base_dir, scope).map(Some),
+        }
+    }
+
+    pub fn assemble(text: &str, base_dir: &Path, scope: &Scope) -> Result<Body, RunError> {
+        Body::assemble_lines(text, b"\n", base_dir, scope)
+    }
+
+    /// Joins the lines of `text` with `separator`, replacing include lines
+    /// with the referenced file's contents.
+    pub fn assemble_lines(
+        text: &str,
+        separator: &[u8],
+        base_dir: &Path,
+        scope: &Scope,
+    ) -> Result<Body, RunError> {
+        let mut body = Body {
+            segments: Vec::new(),
+            len: 0,
+        };
+        let mut pending: Vec<u8> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            if i > 0 {
+                pending.extend_from_slice(separator);
+            }
+            match parse_include(line) {
+                Some(Include::Raw(path)) => {
+                    let path = base_dir.join(path);
+                    let meta = fs::metadata(&path)
+                        .map_err(|e| RunError::FileRead(path.display().to_string(), e))?;
+                    body.push_bytes(std::mem::take(&mut pending));
+                    body.len += meta.len();
+                    body.segments.push(Segment::File(path));
+                }
+                Some(Include::Substituted(path)) => {
+                    let path = base_dir.join(path);
+                    let content = fs::read_to_string(&path)
+                        .map_err(|e| RunError::FileRead(path.display().to_string(), e))?;
+                    let resolved = substitute(&content, scope).map_err(|name| {
+                        RunError::UnresolvedVariableInFile(name, path.display().to_string())
+                    })?;
+                    pending.extend_from_slice(resolved.as_bytes());
+                }
+                None => pending.extend_from_slice(line.as_bytes()),
+            }
+        }
+        body.push_bytes(pending);
+
+        Ok(body)
+    }
+
+    pub fn push_bytes(&mut self, bytes: Vec<u8>) {
+        if !bytes.is_empty() {
+            self.len += bytes.len() as u64;
+            self.segments.push(Segment::Bytes(bytes));
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn into_reader(self) -> Result<Box<dyn Read>, RunError> {
+        let mut reader: Box<dyn Read> = Box::new(io::empty());
+        for segment in self.segments {
+            reader = match segment {
+                Segment::Bytes(bytes) => Box::new(reader.chain(Cursor::new(bytes))),
+                Segment::File(path) => {
+                    let file = File::open(&path)
+                        .map_err(|e| RunError::FileRead(path.display().to_string(), e))?;
+                    Box::new(reader.chain(file))
+                }
+            };
+        }
+        Ok(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(body: Body) -> Vec<u8> {
+        let mut out = Vec::new();
+        body.into_reader().unwrap().read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn assemble_inline_text() {
+        let body = Body::assemble("{\n  \"a\": 1\n}", Path::new("."), &Scope::default()).unwrap();
+        assert_eq!(body.len(), 12);
+        assert_eq!(read_all(body), b"{\n  \"a\": 1\n}");
+    }
+
+    #[test]
+    fn assemble_streams_included_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join("payload.bin"), [0u8, 159, 146, 150]).unwrap();
+
+        let body = Body::assemble("< ./payload.bin", dir, &Scope::default()).unwrap();
+        assert_eq!(body.len(), 4);
+        assert!(matches!(body.segments[0], Segment::File(_)));
+        assert_eq!(read_all(body), vec![0u8, 159, 146, 150]);
+    }
+
+    #[test]
+    fn assemble_mixes_text_and_includes() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join("part.txt"), "middle").unwrap();
+
+        let body = Body::assemble("start\n< part.txt\nend", dir, &Scope::default()).unwrap();
+        assert_eq!(body.len(), 16);
+        assert_eq!(read_all(body), b"start\nmiddle\nend");
+    }
+
+    #[test]
+    fn assemble_substitutes_variables_in_at_include() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join("template.json"), "{\"user\": \"{{user}}\"}").unwrap();
+        let mut scope = Scope::default();
+        scope.vars.insert("user".to_string(), "alice".to_string());
+
+        let body = Body::assemble("<@ ./template.json", dir, &scope).unwrap();
+        assert_eq!(read_all(body), b"{\"user\": \"alice\"}");
+
+        let err = Body::assemble("<@ ./template.json", dir, &Scope::default()).unwrap_err();
+        assert!(matches!(err, RunError::UnresolvedVariableInFile(ref name, _) if name == "user"));
+    }
+
+    #[test]
+    fn assemble_missing_file_errors() {
+        let err =
+            Body::assemble("< ./missing.json", Path::new("."), &Scope::default()).unwrap_err();
+        assert!(matches!(err, RunError::FileRead(_, _)));
+    }
+
+    #[test]
+    fn assemble_does_not_treat_xml_as_include() {
+        let body = Body::assemble(
+            "<root>\n<item/>\n</root>",
+            Path::new("."),
+            &Scope::default(),
+        )
+        .unwrap();
+        assert_eq!(read_all(body), b"<root>\n<item/>\n</root>");
+    }
+}
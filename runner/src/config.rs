@@ -0,0 +1,318 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use toml::{Table, Value};
+
+use crate::error::RunError;
+use crate::exec::Settings;
+use crate::globals::{workspace_dir, STORE_DIR};
+use crate::parse::parse_duration;
+use crate::proxy::Proxies;
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Environment variables that override the timeouts of the config file.
+const CONNECT_TIMEOUT_VAR: &str = "ZHTTP_CONNECT_TIMEOUT";
+const READ_TIMEOUT_VAR: &str = "ZHTTP_READ_TIMEOUT";
+const TOTAL_TIMEOUT_VAR: &str = "ZHTTP_TIMEOUT";
+
+/// Runner settings from one source: the workspace's `.zhttp/config.toml`,
+/// `ZHTTP_*` environment variables or command-line flags. Unset values fall
+/// through to the next source when merged, and finally to the defaults of
+/// `Settings`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub max_redirects: Option<u32>,
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub total_timeout: Option<Duration>,
+    pub proxy: Option<String>,
+    /// Headers sent with every request that doesn't set them itself.
+    pub headers: Vec<(String, String)>,
+    /// PEM file of CA certificates trusted besides the built-in ones.
+    pub ca_file: Option<PathBuf>,
+    /// Skip TLS certificate verification.
+    pub insecure: bool,
+    /// Keep response bodies compressed.
+    pub raw: bool,
+}
+
+impl Config {
+    /// Loads `.zhttp/config.toml` of the workspace containing `start`. A
+    /// missing file is an empty config.
+    pub fn load(start: &Path) -> Result<Config, RunError> {
+        let path = workspace_dir(start).join(STORE_DIR).join(CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => parse_config(&content, &path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(RunError::FileRead(path.display().to_string(), e)),
+        }
+    }
+
+    /// Reads the timeouts set through `ZHTTP_CONNECT_TIMEOUT`,
+    /// `ZHTTP_READ_TIMEOUT` and `ZHTTP_TIMEOUT`.
+    pub fn from_env() -> Result<Config, RunError> {
+        Config::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Config, RunError> {
+        let duration = |name: &str| -> Result<Option<Duration>, RunError> {
+            match lookup(name).filter(|value| !value.trim().is_empty()) {
+                Some(value) => parse_duration(&value).map(Some).ok_or_else(|| {
+                    RunError::Config(
+                        name.to_string(),
+                        format!(
+                            "expected a duration such as 30, 500 ms or 2 m, got '{}'",
+                            value
+                        ),
+                    )
+                }),
+                None => Ok(None),
+            }
+        };
+        Ok(Config {
+            connect_timeout: duration(CONNECT_TIMEOUT_VAR)?,
+            read_timeout: duration(READ_TIMEOUT_VAR)?,
+            total_timeout: duration(TOTAL_TIMEOUT_VAR)?,
+            ..Config::default()
+        })
+    }
+
+    /// Layers `over` on top of this config: its values win, and its headers
+    /// replace ones of the same name.
+    pub fn merge(mut self, over: Config) -> Config {
+        self.headers.retain(|(name, _)| {
+            !over
+                .headers
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case(name))
+        });
+        self.headers.extend(over.headers);
+        Config {
+            max_redirects: over.max_redirects.or(self.max_redirects),
+            connect_timeout: over.connect_timeout.or(self.connect_timeout),
+            read_timeout: over.read_timeout.or(self.read_timeout),
+            total_timeout: over.total_timeout.or(self.total_timeout),
+            proxy: over.proxy.or(self.proxy),
+            headers: self.headers,
+            ca_file: over.ca_file.or(self.ca_file),
+            insecure: over.insecure || self.insecure,
+            raw: over.raw || self.raw,
+        }
+    }
+
+    pub fn settings(&self) -> Settings {
+        let defaults = Settings::default();
+        Settings {
+            max_redirects: self.max_redirects.unwrap_or(defaults.max_redirects),
+            connect_timeout: self.connect_timeout.or(defaults.connect_timeout),
+            read_timeout: self.read_timeout.or(defaults.read_timeout),
+            total_timeout: self.total_timeout.or(defaults.total_timeout),
+            proxy: Proxies::from_env(self.proxy.clone()),
+            headers: self.headers.clone(),
+            tls: None,
+            raw: self.raw,
+        }
+    }
+}
+
+/// Parses a duration given on the command line.
+pub fn parse_duration_arg(arg: &str) -> Result<Duration, String> {
+    parse_duration(arg).ok_or_else(|| {
+        format!(
+            "expected a duration such as 30, 500 ms or 2 m, got '{}'",
+            arg
+        )
+    })
+}
+
+fn parse_config(content: &str, path: &Path) -> Result<Config, RunError> {
+    let invalid = |msg: String| RunError::Config(path.display().to_string(), msg);
+    let table: Table = content
+        .parse()
+        .map_err(|e: toml::de::Error| invalid(e.message().to_string()))?;
+
+    // Paths are relative to the workspace, the parent of `.zhttp`.
+    let workspace = path
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new("."));
+    let mut config = Config::default();
+    for (key, value) in &table {
+        match (key.as_str(), value) {
+            ("max-redirects", Value::Integer(n)) => {
+                config.max_redirects = Some(u32::try_from(*n).map_err(|_| {
+                    invalid(format!(
+                        "max-redirects must be a positive number, got {}",
+                        n
+                    ))
+                })?);
+            }
+            ("proxy", Value::String(url)) => config.proxy = Some(url.clone()),
+            ("timeouts", Value::Table(timeouts)) => {
+                for (phase, value) in timeouts {
+                    let duration = config_duration(value).ok_or_else(|| {
+                        invalid(format!("invalid duration for timeouts.{}", phase))
+                    })?;
+                    match phase.as_str() {
+                        "connect" => config.connect_timeout = Some(duration),
+                        "read" => config.read_timeout = Some(duration),
+                        "total" => config.total_timeout = Some(duration),
+                        _ => return Err(invalid(format!("unknown timeout '{}'", phase))),
+                    }
+                }
+            }
+            ("headers", Value::Table(headers)) => {
+                for (name, value) in headers {
+                    let Value::String(value) = value else {
+                        return Err(invalid(format!("header '{}' must be a string", name)));
+                    };
+                    config.headers.push((name.clone(), value.clone()));
+                }
+            }
+            ("tls", Value::Table(tls)) => {
+                for (name, value) in tls {
+                    match (name.as_str(), value) {
+                        ("ca-file", Value::String(ca_file)) => {
+                            config.ca_file = Some(workspace.join(ca_file))
+                        }
+                        ("insecure", Value::Boolean(insecure)) => config.insecure = *insecure,
+                        ("ca-file" | "insecure", _) => {
+                            return Err(invalid(format!("'tls.{}' has the wrong type", name)))
+                        }
+                        _ => return Err(invalid(format!("unknown TLS setting '{}'", name))),
+                    }
+                }
+            }
+            ("max-redirects" | "proxy" | "timeouts" | "headers" | "tls", _) => {
+                return Err(invalid(format!("'{}' has the wrong type", key)));
+            }
+            _ => return Err(invalid(format!("unknown setting '{}'", key))),
+        }
+    }
+    Ok(config)
+}
+
+/// A duration in the config file: a number of seconds or a string such as
+/// `"500 ms"`.
+fn config_duration(value: &Value) -> Option<Duration> {
+    match value {
+        Value::Integer(n) => parse_duration(&n.to_string()),
+        Value::Float(n) => parse_duration(&n.to_string()),
+        Value::String(s) => parse_duration(s),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<Config, RunError> {
+        parse_config(content, Path::new(".zhttp/config.toml"))
+    }
+
+    #[test]
+    fn parses_config_file() {
+        let config = parse(
+            r#"
+max-redirects = 10
+proxy = "http://proxy.internal:3128"
+
+[timeouts]
+connect = 5
+read = "1.5s"
+total = "2 m"
+
+[headers]
+User-Agent = "zhttp"
+
+[tls]
+ca-file = "certs/ca.pem"
+insecure = true
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.max_redirects, Some(10));
+        assert_eq!(config.proxy.as_deref(), Some("http://proxy.internal:3128"));
+        assert_eq!(config.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(config.read_timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(config.total_timeout, Some(Duration::from_secs(120)));
+        assert_eq!(
+            config.headers,
+            vec![("User-Agent".to_string(), "zhttp".to_string())]
+        );
+        assert_eq!(config.ca_file, Some(PathBuf::from("certs/ca.pem")));
+        assert!(config.insecure);
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        for content in [
+            "timeout = 5",
+            "[timeouts]\nconnect = 0",
+            "[timeouts]\nwrite = 5",
+            "max-redirects = -1",
+            "proxy = 3128",
+            "[headers]\nAccept = 1",
+            "max-redirects = ",
+            "[tls]\ninsecure = \"yes\"",
+            "[tls]\ncert = \"client.pem\"",
+        ] {
+            assert!(
+                matches!(parse(content), Err(RunError::Config(..))),
+                "{}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn reads_timeouts_from_environment() {
+        let config = Config::from_vars(|name| match name {
+            READ_TIMEOUT_VAR => Some("45".to_string()),
+            TOTAL_TIMEOUT_VAR => Some("500ms".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(config.connect_timeout, None);
+        assert_eq!(config.read_timeout, Some(Duration::from_secs(45)));
+        assert_eq!(config.total_timeout, Some(Duration::from_millis(500)));
+
+        let err = Config::from_vars(|_| Some("soon".to_string())).unwrap_err();
+        assert!(err.to_string().contains(CONNECT_TIMEOUT_VAR));
+    }
+
+    #[test]
+    fn later_sources_win() {
+        let file = Config {
+            max_redirects: Some(10),
+            read_timeout: Some(Duration::from_secs(60)),
+            headers: vec![
+                ("Accept".to_string(), "application/json".to_string()),
+                ("User-Agent".to_string(), "zhttp".to_string()),
+            ],
+            ..Config::default()
+        };
+        let cli = Config {
+            read_timeout: Some(Duration::from_secs(5)),
+            headers: vec![("user-agent".to_string(), "ci".to_string())],
+            ..Config::default()
+        };
+        let settings = file.merge(cli).settings();
+        assert_eq!(settings.max_redirects, 10);
+        assert_eq!(settings.read_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(
+            settings.connect_timeout,
+            Settings::default().connect_timeout
+        );
+        assert_eq!(
+            settings.headers,
+            vec![
+                ("Accept".to_string(), "application/json".to_string()),
+                ("user-agent".to_string(), "ci".to_string()),
+            ]
+        );
+    }
+}
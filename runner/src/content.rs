@@ -0,0 +1,172 @@
+use encoding_rs::{Encoding, UTF_8};
+
+/// The media type and charset of a `Content-Type` header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContentType {
+    /// The media type in lowercase, e.g. `application/json`.
+    pub mime_type: String,
+    pub charset: Option<String>,
+}
+
+impl ContentType {
+    pub fn parse(header: &str) -> ContentType {
+        let mut params = header.split(';');
+        let mime_type = params.next().unwrap_or("").trim().to_ascii_lowercase();
+        let charset = params
+            .filter_map(|p| p.split_once('='))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case("charset"))
+            .map(|(_, v)| v.trim().trim_matches('"').to_string());
+        ContentType { mime_type, charset }
+    }
+
+    /// Whether bodies of this type are text. `None` when the type doesn't
+    /// tell, and the body itself has to be looked at.
+    fn is_text(&self) -> Option<bool> {
+        let mime = self.mime_type.as_str();
+        let (kind, subtype) = mime.split_once('/').unwrap_or((mime, ""));
+        if kind == "text"
+            || ["+json", "+xml", "+yaml"]
+                .iter()
+                .any(|suffix| subtype.ends_with(suffix))
+        {
+            return Some(true);
+        }
+        match (kind, subtype) {
+            (
+                "application",
+                "json"
+                | "xml"
+                | "javascript"
+                | "ecmascript"
+                | "x-www-form-urlencoded"
+                | "yaml"
+                | "x-yaml"
+                | "graphql"
+                | "x-ndjson"
+                | "sql",
+            ) => Some(true),
+            ("image" | "audio" | "video" | "font", _) => Some(false),
+            (
+                "application",
+                "octet-stream" | "pdf" | "zip" | "gzip" | "x-tar" | "x-7z-compressed" | "wasm"
+                | "protobuf" | "x-protobuf" | "msgpack" | "x-msgpack" | "cbor",
+            ) => Some(false),
+            _ if self.charset.is_some() => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a body with this content type should be shown as a hex dump
+/// rather than as text. Bodies of unknown types are binary if they aren't
+/// valid UTF-8 or contain NUL bytes.
+pub fn is_binary(body: &[u8], content_type: &ContentType) -> bool {
+    match content_type.is_text() {
+        Some(text) => !text,
+        None => body.contains(&0) || std::str::from_utf8(body).is_err(),
+    }
+}
+
+/// Decodes a text body using the charset of its content type, UTF-8 when
+/// none is given or the charset is unknown. Invalid sequences become
+/// U+FFFD.
+pub fn decode_text(body: &[u8], content_type: &ContentType) -> String {
+    let encoding = content_type
+        .charset
+        .as_deref()
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+/// Bytes shown in the hex dump of a binary body.
+pub const HEX_PREVIEW: usize = 256;
+
+/// A hex dump of the start of `body`, 16 bytes per line with the printable
+/// ones alongside, as `xxd` and `hexdump -C` show it.
+pub fn hex_dump(body: &[u8], limit: usize) -> Vec<String> {
+    body[..body.len().min(limit)]
+        .chunks(16)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let mut hex = String::new();
+            for i in 0..16 {
+                if i == 8 {
+                    hex.push(' ');
+                }
+                match chunk.get(i) {
+                    Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+                    None => hex.push_str("   "),
+                }
+            }
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}  {} |{}|", line * 16, hex, ascii)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_type() {
+        let content_type = ContentType::parse("Text/HTML; charset=\"Shift_JIS\"");
+        assert_eq!(content_type.mime_type, "text/html");
+        assert_eq!(content_type.charset.as_deref(), Some("Shift_JIS"));
+        assert_eq!(ContentType::parse(""), ContentType::default());
+    }
+
+    #[test]
+    fn tells_binary_from_text() {
+        let ct = ContentType::parse;
+        assert!(is_binary(b"\x89PNG", &ct("image/png")));
+        assert!(is_binary(b"plain", &ct("application/octet-stream")));
+        assert!(!is_binary(b"<svg/>", &ct("image/svg+xml")));
+        assert!(!is_binary(b"{}", &ct("application/problem+json")));
+        assert!(!is_binary(b"caf\xe9", &ct("text/plain; charset=latin1")));
+        // Unknown types are sniffed.
+        assert!(!is_binary(b"hello", &ct("")));
+        assert!(is_binary(b"a\0b", &ct("application/x-custom")));
+        assert!(is_binary(b"\xff\xfe", &ct("")));
+    }
+
+    #[test]
+    fn decodes_charsets() {
+        let ct = ContentType::parse;
+        assert_eq!(
+            decode_text(b"caf\xe9", &ct("text/plain; charset=iso-8859-1")),
+            "café"
+        );
+        assert_eq!(
+            decode_text(b"\x93\xfa\x96\x7b", &ct("text/plain; charset=shift_jis")),
+            "日本"
+        );
+        assert_eq!(decode_text("café".as_bytes(), &ct("text/plain")), "café");
+        assert_eq!(decode_text(b"caf\xe9", &ct("text/plain")), "caf\u{fffd}");
+        assert_eq!(decode_text(b"ok", &ct("text/plain; charset=bogus")), "ok");
+    }
+
+    #[test]
+    fn dumps_hex() {
+        let body: Vec<u8> = (0x41..0x41 + 20).chain([0, 0xff]).collect();
+        assert_eq!(
+            hex_dump(&body, HEX_PREVIEW),
+            vec![
+                "00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|",
+                "00000010  51 52 53 54 00 ff                                 |QRST..|",
+            ]
+        );
+        assert_eq!(hex_dump(&body, 16).len(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+use std::convert::Infallible;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cookie_store::{Cookie, CookieStore};
+use serde_json::{Map, Value};
+
+use crate::error::RunError;
+use crate::globals::{workspace_dir, STORE_DIR};
+
+const COOKIE_FILE: &str = "cookies.json";
+/// Key of the jar used when no environment is selected.
+const DEFAULT_JAR: &str = "$default";
+
+/// Cookies kept in the workspace's `.zhttp/cookies.json`, with a separate
+/// jar for each environment so logins to different servers don't mix.
+pub struct CookieJar {
+    path: PathBuf,
+    key: String,
+}
+
+impl CookieJar {
+    pub fn open(start: &Path, env: Option<&str>) -> CookieJar {
+        CookieJar {
+            path: workspace_dir(start).join(STORE_DIR).join(COOKIE_FILE),
+            key: env.unwrap_or(DEFAULT_JAR).to_string(),
+        }
+    }
+
+    fn read_jars(&self) -> Result<Map<String, Value>, RunError> {
+        let invalid = || RunError::CookieJar(self.path.display().to_string());
+        match fs::read_to_string(&self.path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(Value::Object(jars)) => Ok(jars),
+                _ => Err(invalid()),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Map::new()),
+            Err(e) => Err(RunError::FileRead(self.path.display().to_string(), e)),
+        }
+    }
+
+    /// Loads this environment's cookies, dropping any that have expired.
+    pub fn load(&self) -> Result<CookieStore, RunError> {
+        let Some(jar) = self.read_jars()?.remove(&self.key) else {
+            return Ok(CookieStore::default());
+        };
+        let cookies: Vec<Cookie<'static>> = serde_json::from_value(jar)
+            .map_err(|_| RunError::CookieJar(self.path.display().to_string()))?;
+        Ok(
+            CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, Infallible>), false)
+                .expect("loading cookies cannot fail"),
+        )
+    }
+
+    /// Stores the unexpired cookies of `store`, including session cookies, so
+    /// a login carries over to the next run. Other environments' jars are
+    /// left untouched, and the file is only written when this jar changed.
+    pub fn save(&self, store: &CookieStore) -> Result<(), RunError> {
+        let cookies: Vec<&Cookie<'static>> = store.iter_unexpired().collect();
+        let jar = serde_json::to_value(cookies).expect("cookies serialize");
+
+        let mut jars = self.read_jars()?;
+        let empty = jar.as_array().is_some_and(Vec::is_empty);
+        let unchanged = match jars.get(&self.key) {
+            Some(existing) => *existing == jar,
+            None => empty,
+        };
+        if unchanged {
+            return Ok(());
+        }
+        if empty {
+            jars.remove(&self.key);
+        } else {
+            jars.insert(self.key.clone(), jar);
+        }
+
+        let write_err = |e| RunError::FileWrite(self.path.display().to_string(), e);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(write_err)?;
+        }
+        let content = serde_json::to_string_pretty(&jars).expect("cookie jars serialize");
+        fs::write(&self.path, content + "\n").map_err(write_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with(cookies: &[(&str, &str)]) -> CookieStore {
+        let mut store = CookieStore::default();
+        for (set_cookie, url) in cookies {
+            let url: url::Url = url.parse().unwrap();
+            store.parse(set_cookie, &url).expect("test cookie is valid");
+        }
+        store
+    }
+
+    fn names(store: &CookieStore) -> Vec<String> {
+        let mut names: Vec<String> = store
+            .iter_unexpired()
+            .map(|c| c.name().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn jars_round_trip_per_environment() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let dev = CookieJar::open(dir, Some("dev"));
+        let prod = CookieJar::open(dir, Some("prod"));
+        assert!(dev.load().unwrap().iter_any().next().is_none());
+
+        dev.save(&store_with(&[
+            ("session=abc; Path=/", "https://dev.example.com/login"),
+            (
+                "remember=1; Max-Age=3600; Path=/api",
+                "https://dev.example.com/login",
+            ),
+        ]))
+        .unwrap();
+        prod.save(&store_with(&[("session=xyz", "https://example.com/")]))
+            .unwrap();
+
+        let loaded = dev.load().unwrap();
+        assert_eq!(names(&loaded), vec!["remember", "session"]);
+        let api: url::Url = "https://dev.example.com/api/users".parse().unwrap();
+        assert_eq!(loaded.get_request_values(&api).count(), 2);
+        let other: url::Url = "https://other.example.com/api".parse().unwrap();
+        assert_eq!(loaded.get_request_values(&other).count(), 0);
+
+        assert_eq!(names(&prod.load().unwrap()), vec!["session"]);
+        assert_eq!(
+            names(&CookieJar::open(dir, None).load().unwrap()),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn invalid_file_is_an_error() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join(STORE_DIR)).unwrap();
+        fs::write(dir.join(STORE_DIR).join(COOKIE_FILE), "[]").unwrap();
+        let err = CookieJar::open(dir, None).load().unwrap_err();
+        assert!(matches!(err, RunError::CookieJar(_)));
+    }
+}
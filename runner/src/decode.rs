@@ -0,0 +1,112 @@
+use std::io::Read;
+
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+
+use crate::error::RunError;
+
+/// Sent as `Accept-Encoding` by requests that don't set it.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// Undoes the `Content-Encoding` of a response body. Encodings are listed in
+/// the order they were applied, so they are removed from last to first.
+/// Returns `None` when an encoding is unknown, leaving the body as received.
+pub fn decode(body: &[u8], content_encoding: &str) -> Result<Option<Vec<u8>>, RunError> {
+    let mut decoded = body.to_vec();
+    for encoding in content_encoding.rsplit(',') {
+        let encoding = encoding.trim().to_ascii_lowercase();
+        let failed = |e: std::io::Error| RunError::Decode(encoding.clone(), e.to_string());
+        decoded = match encoding.as_str() {
+            "" | "identity" => continue,
+            "gzip" | "x-gzip" => read_all(MultiGzDecoder::new(&decoded[..])).map_err(failed)?,
+            // `deflate` is meant to be zlib-wrapped, but some servers send a
+            // raw deflate stream instead.
+            "deflate" if is_zlib(&decoded) => {
+                read_all(ZlibDecoder::new(&decoded[..])).map_err(failed)?
+            }
+            "deflate" => read_all(DeflateDecoder::new(&decoded[..])).map_err(failed)?,
+            "br" => read_all(brotli_decompressor::Decompressor::new(&decoded[..], 4096))
+                .map_err(failed)?,
+            "zstd" => {
+                let decoder = ruzstd::decoding::StreamingDecoder::new(&decoded[..])
+                    .map_err(|e| RunError::Decode(encoding.clone(), e.to_string()))?;
+                read_all(decoder).map_err(failed)?
+            }
+            _ => return Ok(None),
+        };
+    }
+    Ok(Some(decoded))
+}
+
+fn read_all(mut reader: impl Read) -> std::io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Whether `data` starts with a zlib header: deflate compression and a
+/// checksum that makes the first two bytes a multiple of 31.
+fn is_zlib(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+
+    use super::*;
+
+    const TEXT: &[u8] = b"{\"message\": \"hello, hello, hello\"}";
+
+    fn decoded(body: &[u8], encoding: &str) -> Vec<u8> {
+        decode(body, encoding).unwrap().unwrap()
+    }
+
+    #[test]
+    fn decodes_each_encoding() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(TEXT).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(decoded(&gzip, "gzip"), TEXT);
+        assert_eq!(decoded(&gzip, "X-Gzip"), TEXT);
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TEXT).unwrap();
+        assert_eq!(decoded(&zlib.finish().unwrap(), "deflate"), TEXT);
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(TEXT).unwrap();
+        assert_eq!(decoded(&raw.finish().unwrap(), "deflate"), TEXT);
+
+        // "hello" compressed with brotli.
+        assert_eq!(decoded(b"\x0b\x02\x80hello\x03", "br"), b"hello");
+
+        let zstd =
+            ruzstd::encoding::compress_to_vec(TEXT, ruzstd::encoding::CompressionLevel::Fastest);
+        assert_eq!(decoded(&zstd, "zstd"), TEXT);
+
+        assert_eq!(decoded(TEXT, "identity"), TEXT);
+    }
+
+    #[test]
+    fn removes_stacked_encodings_in_reverse() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TEXT).unwrap();
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&zlib.finish().unwrap()).unwrap();
+        assert_eq!(decoded(&gzip.finish().unwrap(), "deflate, gzip"), TEXT);
+    }
+
+    #[test]
+    fn unknown_and_corrupt_bodies() {
+        assert_eq!(decode(TEXT, "compress").unwrap(), None);
+        assert!(matches!(
+            decode(TEXT, "gzip"),
+            Err(RunError::Decode(encoding, _)) if encoding == "gzip"
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::distr::Alphanumeric;
+use rand::Rng;
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+use crate::env::{find_upwards, http_file_dir};
+use crate::error::RunError;
+use crate::vars::Variables;
+
+const DOTENV_FILE: &str = ".env";
+
+/// Evaluates a dynamic variable expression such as `$randomInt 1 100`.
+/// Returns `None` for unknown variables or invalid arguments.
+pub fn evaluate(expr: &str, dotenv: &Variables) -> Option<String> {
+    let mut parts = expr.split_whitespace();
+    let name = parts.next()?;
+    let args: Vec<&str> = parts.collect();
+
+    match (name, args.as_slice()) {
+        ("$uuid" | "$random.uuid", []) => Some(uuid::Uuid::new_v4().to_string()),
+        ("$timestamp", []) => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+            Some(now.as_secs().to_string())
+        }
+        ("$isoTimestamp", []) => {
+            let format = format_description!(
+                "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z"
+            );
+            OffsetDateTime::now_utc().format(&format).ok()
+        }
+        ("$randomInt" | "$random.integer", []) => {
+            Some(rand::rng().random_range(0..1000).to_string())
+        }
+        ("$randomInt" | "$random.integer", [min, max]) => {
+            let min: i64 = min.parse().ok()?;
+            let max: i64 = max.parse().ok()?;
+            if min >= max {
+                return None;
+            }
+            Some(rand::rng().random_range(min..max).to_string())
+        }
+        ("$random.email", []) => {
+            let user: String = rand::rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(|c| char::from(c).to_ascii_lowercase())
+                .collect();
+            Some(format!("{}@example.com", user))
+        }
+        ("$processEnv", [var]) => std::env::var(var).ok(),
+        ("$dotenv", [key]) => dotenv.get(*key).cloned(),
+        _ => None,
+    }
+}
+
+/// Reads the nearest `.env` file above the .http file, if any.
+pub fn load_dotenv(http_file: &Path) -> Result<Variables, RunError> {
+    let Some(path) = find_upwards(&http_file_dir(http_file), DOTENV_FILE) else {
+        return Ok(Variables::new());
+    };
+    let content =
+        fs::read_to_string(&path).map_err(|e| RunError::FileRead(path.display().to_string(), e))?;
+    Ok(parse_dotenv(&content))
+}
+
+fn parse_dotenv(content: &str) -> Variables {
+    let mut vars = Variables::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        vars.insert(key.trim().to_string(), value.to_string());
+    }
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate_uuid_is_fresh_v4() {
+        let a = evaluate("$uuid", &Variables::new()).unwrap();
+        let b = evaluate("$random.uuid", &Variables::new()).unwrap();
+        assert_eq!(a.len(), 36);
+        assert_eq!(&a[14..15], "4");
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn evaluate_timestamps() {
+        let ts: u64 = evaluate("$timestamp", &Variables::new())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(ts > 1_600_000_000);
+
+        let iso = evaluate("$isoTimestamp", &Variables::new()).unwrap();
+        assert_eq!(iso.len(), 24);
+        assert!(iso.ends_with('Z'));
+        assert_eq!(&iso[10..11], "T");
+    }
+
+    #[test]
+    fn evaluate_random_int_respects_bounds() {
+        for _ in 0..50 {
+            let n: i64 = evaluate("$randomInt 5 8", &Variables::new())
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!((5..8).contains(&n));
+        }
+        assert!(evaluate("$randomInt 8 5", &Variables::new()).is_none());
+        assert!(evaluate("$randomInt a b", &Variables::new()).is_none());
+    }
+
+    #[test]
+    fn evaluate_random_email() {
+        let email = evaluate("$random.email", &Variables::new()).unwrap();
+        assert!(email.ends_with("@example.com"));
+    }
+
+    #[test]
+    fn evaluate_process_env_and_dotenv() {
+        assert_eq!(
+            evaluate("$processEnv PATH", &Variables::new()),
+            std::env::var("PATH").ok()
+        );
+        let dotenv = parse_dotenv("API_KEY=abc");
+        assert_eq!(evaluate("$dotenv API_KEY", &dotenv).as_deref(), Some("abc"));
+        assert!(evaluate("$dotenv MISSING", &dotenv).is_none());
+    }
+
+    #[test]
+    fn evaluate_unknown_is_none() {
+        assert!(evaluate("$nope", &Variables::new()).is_none());
+        assert!(evaluate("$uuid extra", &Variables::new()).is_none());
+    }
+
+    #[test]
+    fn parse_dotenv_handles_comments_quotes_and_export() {
+        let vars = parse_dotenv("# comment\nexport A=1\nB=\"two words\"\nC='x'\ninvalid\n");
+        assert_eq!(vars["A"], "1");
+        assert_eq!(vars["B"], "two words");
+        assert_eq!(vars["C"], "x");
+        assert_eq!(vars.len(), 3);
+    }
+}
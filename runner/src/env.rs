@@ -0,0 +1,329 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::error::RunError;
+use crate::vars::Variables;
+
+pub const PUBLIC_ENV_FILE: &str = "http-client.env.json";
+pub const PRIVATE_ENV_FILE: &str = "http-client.private.env.json";
+const SHARED_SECTION: &str = "$shared";
+const SSL_SECTION: &str = "SSLConfiguration";
+
+type EnvFile = Map<String, Value>;
+
+/// Looks for `file_name` in `start` and each of its ancestors, returning the
+/// nearest match.
+pub fn find_upwards(start: &Path, file_name: &str) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// The directory holding `http_file`, as an absolute path. A relative path
+/// like `api.http` has an empty parent, which searches upwards from it would
+/// never get past.
+pub fn http_file_dir(http_file: &Path) -> PathBuf {
+    let path = fs::canonicalize(http_file)
+        .or_else(|_| std::path::absolute(http_file))
+        .unwrap_or_else(|_| http_file.to_path_buf());
+    path.parent()
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+}
+
+/// Client certificate settings from the `SSLConfiguration` object of an
+/// environment, as JetBrains' HTTP client writes them. Paths are resolved
+/// against the directory of the env file that sets them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SslConfiguration {
+    pub client_certificate: Option<PathBuf>,
+    pub client_certificate_key: Option<PathBuf>,
+    pub has_certificate_passphrase: bool,
+    pub verify_host_certificate: bool,
+}
+
+impl Default for SslConfiguration {
+    fn default() -> SslConfiguration {
+        SslConfiguration {
+            client_certificate: None,
+            client_certificate_key: None,
+            has_certificate_passphrase: false,
+            verify_host_certificate: true,
+        }
+    }
+}
+
+/// The public and then the private env file nearest to `http_file`.
+fn env_files(http_file: &Path) -> Result<Vec<(PathBuf, EnvFile)>, RunError> {
+    let dir = http_file_dir(http_file);
+    let mut files = Vec::new();
+    for name in [PUBLIC_ENV_FILE, PRIVATE_ENV_FILE] {
+        if let Some(path) = find_upwards(&dir, name) {
+            let file = read_env_file(&path)?;
+            files.push((path, file));
+        }
+    }
+    Ok(files)
+}
+
+pub fn load_environment(http_file: &Path, env: Option<&str>) -> Result<Variables, RunError> {
+    let files: Vec<EnvFile> = env_files(http_file)?
+        .into_iter()
+        .map(|(_, file)| file)
+        .collect();
+    merge_environment(&files, env)
+}
+
+/// Loads the `SSLConfiguration` of the `$shared` section and of `env`,
+/// merged in the same order as variables.
+pub fn load_ssl_configuration(
+    http_file: &Path,
+    env: Option<&str>,
+) -> Result<SslConfiguration, RunError> {
+    let mut ssl = SslConfiguration::default();
+    let files = env_files(http_file)?;
+    for section in std::iter::once(SHARED_SECTION).chain(env) {
+        for (path, file) in &files {
+            let Some(config) = file.get(section).and_then(|values| values.get(SSL_SECTION)) else {
+                continue;
+            };
+            let dir = path.parent().unwrap_or(Path::new("."));
+            merge_ssl_configuration(&mut ssl, config, dir)
+                .map_err(|msg| RunError::EnvFile(path.display().to_string(), msg))?;
+        }
+    }
+    Ok(ssl)
+}
+
+fn merge_ssl_configuration(
+    ssl: &mut SslConfiguration,
+    config: &Value,
+    dir: &Path,
+) -> Result<(), String> {
+    let Value::Object(config) = config else {
+        return Err(format!("{} must be an object", SSL_SECTION));
+    };
+    for (key, value) in config {
+        let invalid = || format!("invalid {}.{}", SSL_SECTION, key);
+        match key.as_str() {
+            "clientCertificate" | "clientCertificateKey" => {
+                // Either a path, or `{"path": ..., "format": ...}`.
+                let path = match value {
+                    Value::String(path) => path,
+                    Value::Object(file) => match file.get("path") {
+                        Some(Value::String(path)) => path,
+                        _ => return Err(invalid()),
+                    },
+                    _ => return Err(invalid()),
+                };
+                let path = Some(dir.join(path));
+                if key == "clientCertificate" {
+                    ssl.client_certificate = path;
+                } else {
+                    ssl.client_certificate_key = path;
+                }
+            }
+            "hasCertificatePassphrase" => {
+                ssl.has_certificate_passphrase = value.as_bool().ok_or_else(invalid)?
+            }
+            "verifyHostCertificate" => {
+                ssl.verify_host_certificate = value.as_bool().ok_or_else(invalid)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_env_file(path: &Path) -> Result<EnvFile, RunError> {
+    let content =
+        fs::read_to_string(path).map_err(|e| RunError::FileRead(path.display().to_string(), e))?;
+    match serde_json::from_str(&content) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(RunError::EnvFile(
+            path.display().to_string(),
+            "top level must be an object of environments".to_string(),
+        )),
+        Err(e) => Err(RunError::EnvFile(path.display().to_string(), e.to_string())),
+    }
+}
+
+/// Merges the `$shared` section and then the selected environment of every
+/// file in order, so later files (the private one) win.
+fn merge_environment(files: &[EnvFile], env: Option<&str>) -> Result<Variables, RunError> {
+    if let Some(name) = env {
+        if !files.iter().any(|file| file.contains_key(name)) {
+            let mut available: Vec<&str> = files
+                .iter()
+                .flat_map(|file| file.keys())
+                .map(String::as_str)
+                .filter(|key| *key != SHARED_SECTION)
+                .collect();
+            available.sort_unstable();
+            available.dedup();
+            return Err(RunError::UnknownEnvironment(
+                name.to_string(),
+                available.join(", "),
+            ));
+        }
+    }
+
+    let mut vars = Variables::new();
+    let sections = std::iter::once(SHARED_SECTION).chain(env);
+    for section in sections {
+        for file in files {
+            if let Some(Value::Object(values)) = file.get(section) {
+                merge_section(&mut vars, values);
+            }
+        }
+    }
+    Ok(vars)
+}
+
+fn merge_section(vars: &mut Variables, values: &Map<String, Value>) {
+    for (name, value) in values {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => continue,
+        };
+        vars.insert(name.clone(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_file(json: &str) -> EnvFile {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn merge_environment_private_wins() {
+        let public = env_file(r#"{"dev": {"host": "dev.example.com", "user": "alice"}}"#);
+        let private = env_file(r#"{"dev": {"user": "bob", "password": "secret"}}"#);
+        let vars = merge_environment(&[public, private], Some("dev")).unwrap();
+        assert_eq!(vars["host"], "dev.example.com");
+        assert_eq!(vars["user"], "bob");
+        assert_eq!(vars["password"], "secret");
+    }
+
+    #[test]
+    fn merge_environment_selects_named_env() {
+        let public =
+            env_file(r#"{"dev": {"host": "dev.example.com"}, "prod": {"host": "example.com"}}"#);
+        let vars = merge_environment(&[public], Some("prod")).unwrap();
+        assert_eq!(vars["host"], "example.com");
+    }
+
+    #[test]
+    fn merge_environment_env_overrides_shared() {
+        let public =
+            env_file(r#"{"$shared": {"host": "shared", "version": "v1"}, "dev": {"host": "dev"}}"#);
+        let vars = merge_environment(&[public], Some("dev")).unwrap();
+        assert_eq!(vars["host"], "dev");
+        assert_eq!(vars["version"], "v1");
+    }
+
+    #[test]
+    fn merge_environment_without_env_uses_shared_only() {
+        let public = env_file(r#"{"$shared": {"a": "1"}, "dev": {"b": "2"}}"#);
+        let vars = merge_environment(&[public], None).unwrap();
+        assert_eq!(vars.len(), 1);
+        assert_eq!(vars["a"], "1");
+    }
+
+    #[test]
+    fn merge_environment_stringifies_scalars_and_skips_objects() {
+        let public = env_file(
+            r#"{"dev": {"port": 8080, "debug": true, "SSLConfiguration": {"verifyHostCertificate": false}}}"#,
+        );
+        let vars = merge_environment(&[public], Some("dev")).unwrap();
+        assert_eq!(vars["port"], "8080");
+        assert_eq!(vars["debug"], "true");
+        assert!(!vars.contains_key("SSLConfiguration"));
+    }
+
+    #[test]
+    fn ssl_configuration_resolves_paths_per_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let project = dir.join("project");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            dir.join(PUBLIC_ENV_FILE),
+            r#"{"$shared": {"SSLConfiguration": {"verifyHostCertificate": false}},
+                "dev": {"SSLConfiguration": {"clientCertificate": "certs/client.pem"}}}"#,
+        )
+        .unwrap();
+        fs::write(
+            project.join(PRIVATE_ENV_FILE),
+            r#"{"dev": {"SSLConfiguration": {
+                "clientCertificateKey": {"path": "client.key", "format": "PEM"},
+                "hasCertificatePassphrase": true}}}"#,
+        )
+        .unwrap();
+        let http_file = project.join("api.http");
+
+        let ssl = load_ssl_configuration(&http_file, Some("dev")).unwrap();
+        assert_eq!(
+            ssl,
+            SslConfiguration {
+                client_certificate: Some(dir.join("certs/client.pem")),
+                client_certificate_key: Some(project.join("client.key")),
+                has_certificate_passphrase: true,
+                verify_host_certificate: false,
+            }
+        );
+        let shared = load_ssl_configuration(&http_file, None).unwrap();
+        assert_eq!(shared.client_certificate, None);
+        assert!(!shared.verify_host_certificate);
+
+        fs::write(
+            project.join(PRIVATE_ENV_FILE),
+            r#"{"dev": {"SSLConfiguration": {"verifyHostCertificate": "no"}}}"#,
+        )
+        .unwrap();
+        let err = load_ssl_configuration(&http_file, Some("dev")).unwrap_err();
+        assert!(matches!(err, RunError::EnvFile(..)));
+    }
+
+    #[test]
+    fn relative_http_file_searches_from_current_dir() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(http_file_dir(Path::new("api.http")), cwd);
+        assert_eq!(http_file_dir(Path::new("./api/a.http")), cwd.join("api"));
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let project = dir.join("api");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(dir.join(PUBLIC_ENV_FILE), r#"{"dev": {"host": "dev"}}"#).unwrap();
+        fs::write(project.join("a.http"), "GET http://{{host}}/").unwrap();
+        // Paths through `..` resolve to the real directory.
+        let http_file = project.join("../api/a.http");
+        assert_eq!(
+            http_file_dir(&http_file),
+            fs::canonicalize(&project).unwrap()
+        );
+        let vars = load_environment(&http_file, Some("dev")).unwrap();
+        assert_eq!(vars["host"], "dev");
+    }
+
+    #[test]
+    fn merge_environment_unknown_env_lists_available() {
+        let public = env_file(r#"{"$shared": {}, "prod": {}, "dev": {}}"#);
+        let err = merge_environment(&[public], Some("staging")).unwrap_err();
+        match err {
+            RunError::UnknownEnvironment(name, available) => {
+                assert_eq!(name, "staging");
+                assert_eq!(available, "dev, prod");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+}
@@ -1,21 +1,142 @@
 use std::fmt;
+use std::time::Duration;
+
+use zhttp_shared::DYNAMIC_VARIABLES;
 
 #[derive(Debug)]
 pub enum RunError {
     FileRead(String, std::io::Error),
+    FileWrite(String, std::io::Error),
+    EnvFile(String, String),
+    UnknownEnvironment(String, String),
     NoRequestBlock(usize),
+    UnknownRequestName(String, Vec<String>),
     ParseFailed(String),
+    UnresolvedVariable(String, usize),
+    UnresolvedVariableInFile(String, String),
+    Multipart(String),
+    Script(String, String),
+    GlobalStore(String),
+    UnknownGlobal(String, String),
+    CookieJar(String),
+    InvalidAssertion(String, String),
+    TestFiles(String, String),
+    TooManyRedirects(u32),
+    Config(String, String),
+    Proxy(String, String),
+    Tls(String),
+    UnsupportedVersion(String, String),
+    Timeout(TimeoutPhase, Duration),
+    Decode(String, String),
     Transport(String),
 }
 
+/// The part of an exchange that took too long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeoutPhase {
+    Connect,
+    Read,
+    Total,
+}
+
 impl fmt::Display for RunError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             RunError::FileRead(path, e) => write!(f, "Error reading {}: {}", path, e),
+            RunError::FileWrite(path, e) => write!(f, "Error writing {}: {}", path, e),
+            RunError::EnvFile(path, msg) => write!(f, "Invalid environment file {}: {}", path, msg),
+            RunError::UnknownEnvironment(name, available) if available.is_empty() => {
+                write!(
+                    f,
+                    "Environment '{}' not found: no environments defined",
+                    name
+                )
+            }
+            RunError::UnknownEnvironment(name, available) => write!(
+                f,
+                "Environment '{}' not found (available: {})",
+                name, available
+            ),
             RunError::NoRequestBlock(line) => {
                 write!(f, "No request block found at line {}", line)
             }
+            RunError::UnknownRequestName(name, suggestions) if suggestions.is_empty() => {
+                write!(f, "No request named '{}'", name)
+            }
+            RunError::UnknownRequestName(name, suggestions) => write!(
+                f,
+                "No request named '{}' (did you mean: {}?)",
+                name,
+                suggestions.join(", ")
+            ),
             RunError::ParseFailed(msg) => write!(f, "Parse error: {}", msg),
+            RunError::UnresolvedVariable(name, line) if name.starts_with('$') => {
+                let available: Vec<&str> = DYNAMIC_VARIABLES.iter().map(|(n, _)| *n).collect();
+                write!(
+                    f,
+                    "Cannot evaluate {{{{{}}}}} at line {} (dynamic variables: {})",
+                    name,
+                    line,
+                    available.join(", ")
+                )
+            }
+            RunError::UnresolvedVariable(name, line) => {
+                write!(f, "Unresolved variable {{{{{}}}}} at line {}", name, line)
+            }
+            RunError::UnresolvedVariableInFile(name, path) => {
+                write!(f, "Unresolved variable {{{{{}}}}} in {}", name, path)
+            }
+            RunError::Multipart(msg) => write!(f, "Invalid multipart body: {}", msg),
+            RunError::Script(origin, msg) => write!(f, "Script error in {}: {}", origin, msg),
+            RunError::GlobalStore(path) => write!(
+                f,
+                "Invalid global variable store {}: expected an object of strings",
+                path
+            ),
+            RunError::CookieJar(path) => write!(
+                f,
+                "Invalid cookie jar {}: expected an object of cookie lists",
+                path
+            ),
+            RunError::UnknownGlobal(name, path) => {
+                write!(f, "Global variable '{}' not found in {}", name, path)
+            }
+            RunError::InvalidAssertion(text, reason) => {
+                write!(f, "Invalid assertion '?? {}': {}", text, reason)
+            }
+            RunError::TestFiles(pattern, msg) => write!(f, "{}: {}", pattern, msg),
+            RunError::TooManyRedirects(max) => write!(
+                f,
+                "Too many redirects: stopped after {} (raise with --max-redirects)",
+                max
+            ),
+            RunError::Config(source, msg) => write!(f, "Invalid setting in {}: {}", source, msg),
+            RunError::Proxy(proxy, msg) => write!(f, "Invalid proxy {}: {}", proxy, msg),
+            RunError::Tls(msg) => write!(f, "TLS configuration error: {}", msg),
+            RunError::UnsupportedVersion(version, msg) => {
+                write!(f, "Cannot use {}: {}", version, msg)
+            }
+            RunError::Timeout(phase, limit) => {
+                let (what, flag) = match phase {
+                    TimeoutPhase::Connect => {
+                        ("connecting", "--connect-timeout or # @connection-timeout")
+                    }
+                    TimeoutPhase::Read => {
+                        ("waiting for the response", "--read-timeout or # @timeout")
+                    }
+                    TimeoutPhase::Total => ("running the request", "--timeout"),
+                };
+                write!(
+                    f,
+                    "Timed out {} after {:?} (raise with {})",
+                    what, limit, flag
+                )
+            }
+            RunError::Decode(encoding, msg) => write!(
+                f,
+                "Cannot decode {} response body, showing it as received: {}",
+                encoding, msg
+            ),
             RunError::Transport(msg) => write!(f, "Transport error: {}", msg),
         }
     }
@@ -1,33 +1,385 @@
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::error::RunError;
-use crate::format::print_response;
+use cookie_store::CookieStore;
+
+use crate::body::Body;
+use crate::content::{self, ContentType};
+use crate::decode::{self, ACCEPT_ENCODING};
+use crate::error::{RunError, TimeoutPhase};
+use crate::format;
 use crate::parse::RequestBlock;
+use crate::proxy::{Proxies, Proxy};
+use crate::timing::Timings;
+use crate::transport::{self, Exchange, Version};
+
+/// User-Agent of requests that don't set one.
+pub const USER_AGENT: &str = concat!("zhttp/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Size of the body as received, when it was decoded from its
+    /// `Content-Encoding`.
+    pub wire_size: Option<usize>,
+    /// Time from sending the first request until this response was read in
+    /// full, including any redirects.
+    pub elapsed: Duration,
+    /// How long each phase of the final request took.
+    pub timings: Timings,
+    /// Redirects followed before this response, in order.
+    pub redirects: Vec<Redirect>,
+    /// The proxy the final request went through.
+    pub proxy: Option<Proxy>,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
 
-pub fn execute_request(req: &RequestBlock) -> Result<(), RunError> {
-    let mut request = ureq::request(&req.method, &req.url);
+    pub fn content_type(&self) -> ContentType {
+        ContentType::parse(self.header("Content-Type").unwrap_or(""))
+    }
 
-    for (name, value) in &req.headers {
-        request = request.set(name, value);
+    /// The body as text, decoded with the charset of its `Content-Type`.
+    pub fn text(&self) -> String {
+        content::decode_text(&self.body, &self.content_type())
     }
 
+    /// Whether the body should be shown as a hex dump. Bodies left
+    /// compressed, with `--raw` or in an unknown encoding, always are.
+    pub fn is_binary(&self) -> bool {
+        let encoded = self.wire_size.is_none()
+            && !self.body.is_empty()
+            && self
+                .header("Content-Encoding")
+                .is_some_and(|encoding| !encoding.trim().eq_ignore_ascii_case("identity"));
+        encoded || content::is_binary(&self.body, &self.content_type())
+    }
+
+    /// Replaces a compressed body with its decoded form.
+    fn decode(&mut self) -> Result<(), RunError> {
+        let Some(encoding) = self.header("Content-Encoding") else {
+            return Ok(());
+        };
+        if self.body.is_empty() {
+            return Ok(());
+        }
+        if let Some(decoded) = decode::decode(&self.body, encoding)? {
+            let wire = std::mem::replace(&mut self.body, decoded);
+            self.wire_size = Some(wire.len());
+        }
+        Ok(())
+    }
+}
+
+/// Runner-wide transport settings, as opposed to the per-request
+/// `# @` directives in `RequestOptions`.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub max_redirects: u32,
+    pub connect_timeout: Option<Duration>,
+    /// How long to wait for each read of the response.
+    pub read_timeout: Option<Duration>,
+    /// Limit for the whole exchange, including redirects and the body.
+    pub total_timeout: Option<Duration>,
+    pub proxy: Proxies,
+    /// Headers added to requests that don't set them.
+    pub headers: Vec<(String, String)>,
+    /// TLS configuration replacing the default one.
+    pub tls: Option<Arc<rustls::ClientConfig>>,
+    /// Keep response bodies as received instead of decoding them.
+    pub raw: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            max_redirects: 5,
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            total_timeout: None,
+            proxy: Proxies::default(),
+            headers: Vec::new(),
+            tls: None,
+            raw: false,
+        }
+    }
+}
+
+/// The timeouts in effect for one request, used to tell which of them
+/// expired when an I/O operation times out.
+pub struct Limits {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub total: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl Limits {
+    pub fn new(
+        connect: Option<Duration>,
+        read: Option<Duration>,
+        total: Option<Duration>,
+        start: Instant,
+    ) -> Limits {
+        Limits {
+            connect,
+            read,
+            total,
+            deadline: total.map(|total| start + total),
+        }
+    }
+
+    /// Time left before the total timeout, or an error if it has passed.
+    pub fn remaining(&self) -> Result<Option<Duration>, RunError> {
+        match (self.deadline, self.total) {
+            (Some(deadline), Some(total)) => {
+                match deadline.checked_duration_since(Instant::now()) {
+                    Some(left) if !left.is_zero() => Ok(Some(left)),
+                    _ => Err(RunError::Timeout(TimeoutPhase::Total, total)),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A transport error, with a hint when the server's certificate was
+/// rejected.
+pub fn transport_failure(mut msg: String) -> RunError {
+    if msg.contains("invalid peer certificate") {
+        msg.push_str(" (trust the server's CA with --ca-file, or skip the check with --insecure)");
+    }
+    RunError::Transport(msg)
+}
+
+/// One redirect response that was followed on the way to the final one.
+#[derive(Debug)]
+pub struct Redirect {
+    pub status: u16,
+    pub status_text: String,
+    pub elapsed: Duration,
+    /// Method of the follow-up request.
+    pub method: String,
+    /// The `Location` header, resolved against the request URL.
+    pub location: String,
+}
+
+fn is_redirect(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// The method and whether the body is kept when following a redirect:
+/// 307 and 308 repeat the request as-is, while 301, 302 and 303 switch to a
+/// body-less GET (HEAD stays HEAD), as browsers do.
+fn redirect_method(status: u16, method: &str) -> (String, bool) {
+    match status {
+        307 | 308 => (method.to_string(), true),
+        _ if method.eq_ignore_ascii_case("HEAD") => (method.to_string(), false),
+        _ => ("GET".to_string(), false),
+    }
+}
+
+fn host(url: &url::Url) -> Option<(String, Option<u16>)> {
+    Some((url.host_str()?.to_string(), url.port_or_known_default()))
+}
+
+/// Sends the request as configured by `settings` and the request's `# @`
+/// directives, which take precedence, following redirects one hop at a time
+/// so each hop can be reported. Cookies are taken from `cookies` and those
+/// the responses set are stored back, unless the request has
+/// `# @no-cookie-jar`.
+pub fn execute_request(
+    req: &RequestBlock,
+    body: Option<Body>,
+    cookies: &mut CookieStore,
+    settings: &Settings,
+) -> Result<Response, RunError> {
+    let options = &req.options;
     let start = Instant::now();
-    let response = if let Some(body) = &req.body {
-        request.send_string(body)
+    let limits = Limits::new(
+        options.connection_timeout.or(settings.connect_timeout),
+        options.timeout.or(settings.read_timeout),
+        settings.total_timeout,
+        start,
+    );
+
+    let max_redirects = if options.no_redirect {
+        0
     } else {
-        request.call()
+        settings.max_redirects
     };
-    let elapsed = start.elapsed();
 
-    match response {
-        Ok(resp) => {
-            print_response(resp, req, elapsed);
-            Ok(())
+    let mut method = req.method.clone();
+    let mut url = req.url.clone();
+    let mut headers = req.headers.clone();
+    for (name, value) in &settings.headers {
+        if !has_header(&req.headers, name) {
+            headers.push((name.clone(), value.clone()));
+        }
+    }
+    // With `--raw` bodies are shown as received, so don't ask for them
+    // compressed.
+    if !settings.raw && !has_header(&headers, "Accept-Encoding") {
+        headers.push(("Accept-Encoding".to_string(), ACCEPT_ENCODING.to_string()));
+    }
+    let mut body = body;
+    let mut redirects = Vec::new();
+
+    let version = Version::from_request(req.http_version.as_deref())?;
+
+    let mut response = loop {
+        let hop_start = Instant::now();
+        let current = url::Url::parse(&url)
+            .map_err(|e| RunError::Transport(format!("invalid URL {}: {}", url, e)))?;
+        let proxy = settings.proxy.for_url(&current)?;
+
+        let mut hop_headers = headers.clone();
+        if !options.no_cookie_jar {
+            add_cookie_header(&mut hop_headers, cookies, &current);
+        }
+        let exchange = Exchange {
+            method: &method,
+            url: &current,
+            headers: &hop_headers,
+            body: body.clone(),
+            limits: &limits,
+            tls: settings.tls.as_ref(),
+            proxy: proxy.as_ref(),
+            start,
+        };
+        let mut resp = transport::send(version, &exchange)?;
+        if !options.no_cookie_jar {
+            for (_, set_cookie) in resp
+                .headers
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case("Set-Cookie"))
+            {
+                let _ = cookies.parse(set_cookie, &current);
+            }
+        }
+        resp.proxy = proxy;
+        if !redirects.is_empty() {
+            resp.timings.redirects = hop_start.duration_since(start);
+        }
+
+        let location = resp.header("Location").map(str::to_string);
+        let (true, Some(location)) = (is_redirect(resp.status), location) else {
+            break resp;
+        };
+        if max_redirects == 0 {
+            break resp;
+        }
+        if redirects.len() as u32 >= max_redirects {
+            return Err(RunError::TooManyRedirects(max_redirects));
+        }
+
+        let next = current.join(&location).map_err(|e| {
+            RunError::Transport(format!("invalid redirect location {}: {}", location, e))
+        })?;
+
+        let status = resp.status;
+        let (next_method, keep_body) = redirect_method(status, &method);
+        if !keep_body {
+            body = None;
+            headers.retain(|(name, _)| {
+                !name.eq_ignore_ascii_case("Content-Type")
+                    && !name.eq_ignore_ascii_case("Content-Length")
+            });
         }
-        Err(ureq::Error::Status(_, resp)) => {
-            print_response(resp, req, elapsed);
-            Ok(())
+        if host(&current) != host(&next) {
+            headers.retain(|(name, _)| !name.eq_ignore_ascii_case("Authorization"));
         }
-        Err(ureq::Error::Transport(e)) => Err(RunError::Transport(e.to_string())),
+
+        method = next_method;
+        url = next.to_string();
+        redirects.push(Redirect {
+            status,
+            status_text: resp.status_text,
+            elapsed: hop_start.elapsed(),
+            method: method.clone(),
+            location: url.clone(),
+        });
+    };
+
+    response.redirects = redirects;
+    // A body that can't be decoded is kept as received, so the response can
+    // still be shown and checked.
+    if let (false, Err(e)) = (settings.raw, response.decode()) {
+        eprintln!("{}WARNING: {}{}", format::YELLOW, e, format::RESET);
+    }
+    Ok(response)
+}
+
+/// Adds the stored cookies for `url` to the `Cookie` header.
+fn add_cookie_header(headers: &mut Vec<(String, String)>, cookies: &CookieStore, url: &url::Url) {
+    let stored: Vec<String> = cookies
+        .get_request_values(url)
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    if stored.is_empty() {
+        return;
+    }
+    match headers
+        .iter_mut()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Cookie"))
+    {
+        Some((_, value)) => *value = format!("{}; {}", value, stored.join("; ")),
+        None => headers.push(("Cookie".to_string(), stored.join("; "))),
+    }
+}
+
+fn has_header(headers: &[(String, String)], name: &str) -> bool {
+    headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_method_follows_status_semantics() {
+        assert_eq!(redirect_method(307, "POST"), ("POST".to_string(), true));
+        assert_eq!(redirect_method(308, "PUT"), ("PUT".to_string(), true));
+        assert_eq!(redirect_method(301, "POST"), ("GET".to_string(), false));
+        assert_eq!(redirect_method(302, "DELETE"), ("GET".to_string(), false));
+        assert_eq!(redirect_method(303, "POST"), ("GET".to_string(), false));
+        assert_eq!(redirect_method(303, "HEAD"), ("HEAD".to_string(), false));
+    }
+
+    #[test]
+    fn corrupt_body_is_kept_as_received() {
+        let mut response = Response {
+            status: 200,
+            status_text: "OK".to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            headers: vec![("Content-Encoding".to_string(), "gzip".to_string())],
+            body: b"not gzip".to_vec(),
+            wire_size: None,
+            elapsed: Duration::ZERO,
+            timings: Timings::default(),
+            redirects: Vec::new(),
+            proxy: None,
+        };
+        assert!(matches!(response.decode(), Err(RunError::Decode(..))));
+        assert_eq!(response.body, b"not gzip");
+        assert!(response.is_binary());
+    }
+
+    #[test]
+    fn only_redirect_statuses_are_followed() {
+        assert!(is_redirect(302));
+        assert!(is_redirect(308));
+        assert!(!is_redirect(300));
+        assert!(!is_redirect(304));
     }
 }
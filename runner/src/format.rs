@@ -1,7 +1,13 @@
-use std::io::Read;
+use std::path::Path;
 use std::time::Duration;
 
+use crate::content::{self, HEX_PREVIEW};
+use crate::exec::Response;
 use crate::parse::RequestBlock;
+use crate::pretty;
+use crate::script::TestResult;
+use crate::session::RequestRun;
+use crate::timing::Timings;
 
 pub const RESET: &str = "\x1b[0m";
 pub const DIM: &str = "\x1b[2m";
@@ -26,61 +32,255 @@ pub fn format_duration(d: Duration) -> String {
     }
 }
 
-pub fn print_response(resp: ureq::Response, req: &RequestBlock, elapsed: Duration) {
-    let status = resp.status();
-    let status_text = resp.status_text().to_string();
-    let resp_version = resp.http_version().to_string();
-
-    let mut header_lines = Vec::new();
-    for name in resp.headers_names() {
-        if let Some(value) = resp.header(&name) {
-            header_lines.push(format!("{}: {}", name, value));
-        }
-    }
+/// Stands in for the response of a `# @no-log` request, which only shows
+/// its status.
+pub fn print_unlogged(resp: &Response) {
+    println!(
+        "{}{} {}{} {}(# @no-log: response not shown){}",
+        status_color(resp.status),
+        resp.status,
+        resp.status_text,
+        RESET,
+        DIM,
+        RESET
+    );
+}
 
-    let mut body = String::new();
-    resp.into_reader().read_to_string(&mut body).ok();
-    let body_len = body.len();
+/// Prints the request line, status, headers and body. `saved` is where a
+/// `>>` redirect wrote the body, which is then not printed. `verbose` adds
+/// how the request was sent.
+pub fn print_response(resp: &Response, req: &RequestBlock, saved: Option<&Path>, verbose: bool) {
+    let status = resp.status;
+    let body_len = resp.body.len();
 
     let color = status_color(status);
 
-    let title = match &req.name {
-        Some(name) => name.clone(),
-        None => format!("{} {}", req.method, req.url),
-    };
-    print!("\x1b]2;{}\x07", title);
+    print!("\x1b]2;{}\x07", req.title());
 
     match &req.http_version {
         Some(ver) => println!("{} {} {}\n", req.method, req.url, ver),
         None => println!("{} {}\n", req.method, req.url),
     }
 
+    if verbose {
+        match &resp.proxy {
+            Some(proxy) => println!("{}Proxy: {}{}\n", DIM, proxy, RESET),
+            None => println!("{}Proxy: none{}\n", DIM, RESET),
+        }
+    }
+
+    for hop in &resp.redirects {
+        println!(
+            "{}{} {} · {}{} → {} {}",
+            status_color(hop.status),
+            hop.status,
+            hop.status_text,
+            format_duration(hop.elapsed),
+            RESET,
+            hop.method,
+            hop.location
+        );
+    }
+    if !resp.redirects.is_empty() {
+        println!();
+    }
+
     println!(
         "{}{} {} {}{}\n",
-        color, resp_version, status, status_text, RESET
+        color, resp.http_version, status, resp.status_text, RESET
     );
 
-    for h in &header_lines {
-        println!("{}{}{}", DIM, h, RESET);
+    for (name, value) in &resp.headers {
+        println!("{}{}: {}{}", DIM, name, value, RESET);
     }
     println!();
 
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
-        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+    if let Some(saved) = saved {
+        println!("{}Response body saved to {}{}", DIM, saved.display(), RESET);
+    } else if resp.is_binary() {
+        print_binary(resp);
     } else {
+        let body = pretty::format_body(&resp.text(), &resp.content_type());
         print!("{}", body);
         if !body.ends_with('\n') {
             println!();
         }
     }
 
+    let size = match (resp.wire_size, resp.header("Content-Encoding")) {
+        (Some(wire), Some(encoding)) => {
+            format!("{} bytes ({}, {} on the wire)", body_len, encoding, wire)
+        }
+        _ => format!("{} bytes", body_len),
+    };
     println!(
-        "\n{}{} {} · {} bytes · {}{}",
+        "\n{}{} {} · {} · {}{}",
         color,
         status,
-        status_text,
-        body_len,
-        format_duration(elapsed),
+        resp.status_text,
+        size,
+        format_duration(resp.elapsed),
+        RESET
+    );
+    for line in waterfall(&resp.timings, resp.elapsed) {
+        println!("{}{}{}", DIM, line, RESET);
+    }
+}
+
+/// Prints the type and size of a binary body and a hex dump of its start.
+fn print_binary(resp: &Response) {
+    let mime_type = resp.content_type().mime_type;
+    let mime_type = if mime_type.is_empty() {
+        "unknown type"
+    } else {
+        &mime_type
+    };
+    println!(
+        "{}Binary body · {} · {} bytes{}",
+        DIM,
+        mime_type,
+        resp.body.len(),
+        RESET
+    );
+    for line in content::hex_dump(&resp.body, HEX_PREVIEW) {
+        println!("{}", line);
+    }
+    if resp.body.len() > HEX_PREVIEW {
+        println!(
+            "{}… {} more bytes; end the request with `>> file` to save them{}",
+            DIM,
+            resp.body.len() - HEX_PREVIEW,
+            RESET
+        );
+    }
+}
+
+const WATERFALL_WIDTH: usize = 30;
+
+/// Like `format_duration`, with a decimal for the short phases of local
+/// or reused connections.
+fn format_phase(d: Duration) -> String {
+    if d < Duration::from_millis(10) {
+        format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+    } else {
+        format_duration(d)
+    }
+}
+
+/// One line per phase of the request, with a bar placed where the phase
+/// falls within the `total` time.
+pub fn waterfall(timings: &Timings, total: Duration) -> Vec<String> {
+    let phases = timings.phases();
+    let scale = phases
+        .iter()
+        .map(|(_, duration)| *duration)
+        .sum::<Duration>()
+        .max(total)
+        .as_secs_f64();
+    let cells = |duration: Duration| {
+        if scale == 0.0 {
+            0
+        } else {
+            (duration.as_secs_f64() / scale * WATERFALL_WIDTH as f64).round() as usize
+        }
+    };
+
+    let mut offset = Duration::ZERO;
+    phases
+        .iter()
+        .map(|(name, duration)| {
+            let start = cells(offset).min(WATERFALL_WIDTH - 1);
+            offset += *duration;
+            let len = cells(offset)
+                .saturating_sub(start)
+                .clamp(1, WATERFALL_WIDTH - start);
+            format!(
+                "{:<9} {:>7}  {}{}",
+                name,
+                format_phase(*duration),
+                " ".repeat(start),
+                "█".repeat(len)
+            )
+        })
+        .collect()
+}
+
+pub fn print_test_results(tests: &[TestResult]) {
+    if tests.is_empty() {
+        return;
+    }
+    println!();
+    for test in tests {
+        match (&test.passed, &test.message) {
+            (true, _) => println!("{}✓ {}{}", GREEN, test.name, RESET),
+            (false, Some(message)) => println!("{}✗ {}: {}{}", RED, test.name, message, RESET),
+            (false, None) => println!("{}✗ {}{}", RED, test.name, RESET),
+        }
+    }
+}
+
+/// Prints one row per request with its start line, title, status, duration
+/// and how many checks passed, followed by the failed checks.
+pub fn print_summary(runs: &[RequestRun]) {
+    let rows: Vec<[String; 4]> = runs
+        .iter()
+        .map(|run| match &run.result {
+            Ok(outcome) => {
+                let passed = outcome.results.iter().filter(|r| r.passed).count();
+                let checks = match outcome.results.len() {
+                    0 => "-".to_string(),
+                    total => format!("{}/{} passed", passed, total),
+                };
+                [
+                    format!("{} {}", run.line, run.name),
+                    outcome.status.to_string(),
+                    format_duration(outcome.elapsed),
+                    checks,
+                ]
+            }
+            Err(e) => [
+                format!("{} {}", run.line, run.name),
+                "error".to_string(),
+                "-".to_string(),
+                e.to_string(),
+            ],
+        })
+        .collect();
+
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let (request_width, status_width, time_width) = (width(0), width(1), width(2));
+
+    for (run, [request, status, time, checks]) in runs.iter().zip(&rows) {
+        let color = match &run.result {
+            Ok(outcome) if outcome.passed() => status_color(outcome.status),
+            _ => RED,
+        };
+        println!(
+            "{}{:<request_width$}  {:>status_width$}  {:>time_width$}  {}{}",
+            color, request, status, time, checks, RESET
+        );
+        if let Ok(outcome) = &run.result {
+            for failed in outcome.results.iter().filter(|r| !r.passed) {
+                match &failed.message {
+                    Some(message) => println!("{}  ✗ {}: {}{}", RED, failed.name, message, RESET),
+                    None => println!("{}  ✗ {}{}", RED, failed.name, RESET),
+                }
+            }
+        }
+    }
+
+    let failed = runs.iter().filter(|run| !run.passed()).count();
+    let color = if failed == 0 { GREEN } else { RED };
+    println!(
+        "\n{}{} requests, {} failed{}",
+        color,
+        runs.len(),
+        failed,
         RESET
     );
 }
@@ -130,4 +330,28 @@ mod tests {
         assert_eq!(format_duration(Duration::from_millis(1500)), "1.50s");
         assert_eq!(format_duration(Duration::from_millis(2345)), "2.34s");
     }
+
+    #[test]
+    fn waterfall_places_phases_in_order() {
+        let ms = Duration::from_millis;
+        let timings = Timings {
+            dns: Some(ms(0)),
+            connect: Some(ms(10)),
+            tls: Some(ms(20)),
+            wait: ms(60),
+            download: ms(10),
+            ..Timings::default()
+        };
+        let lines = waterfall(&timings, ms(100));
+        assert_eq!(
+            lines,
+            vec![
+                format!("DNS         0.0ms  {}", "█"),
+                format!("Connect      10ms  {}", "█".repeat(3)),
+                format!("TLS          20ms     {}", "█".repeat(6)),
+                format!("Wait         60ms  {}{}", " ".repeat(9), "█".repeat(18)),
+                format!("Download     10ms  {}{}", " ".repeat(27), "█".repeat(3)),
+            ]
+        );
+    }
 }
@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::env::{find_upwards, PUBLIC_ENV_FILE};
+use crate::error::RunError;
+use crate::vars::Variables;
+
+pub const STORE_DIR: &str = ".zhttp";
+const STORE_FILE: &str = "globals.json";
+
+/// Finds the workspace directory for `start`: the nearest ancestor that
+/// already has a `.zhttp` directory, else the one holding
+/// `http-client.env.json`, else `start` itself.
+pub fn workspace_dir(start: &Path) -> PathBuf {
+    if let Some(dir) = start.ancestors().find(|dir| dir.join(STORE_DIR).is_dir()) {
+        return dir.to_path_buf();
+    }
+    find_upwards(start, PUBLIC_ENV_FILE)
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| start.to_path_buf())
+}
+
+/// `client.global` values, kept in `.zhttp/globals.json` of the workspace so
+/// they survive between runs.
+pub struct GlobalStore {
+    path: PathBuf,
+    saved: Variables,
+    pub values: Variables,
+}
+
+impl GlobalStore {
+    /// Loads the store of the workspace containing `start`.
+    pub fn load(start: &Path) -> Result<GlobalStore, RunError> {
+        let path = workspace_dir(start).join(STORE_DIR).join(STORE_FILE);
+
+        let values = match fs::read_to_string(&path) {
+            Ok(content) => parse_store(&content)
+                .ok_or_else(|| RunError::GlobalStore(path.display().to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Variables::new(),
+            Err(e) => return Err(RunError::FileRead(path.display().to_string(), e)),
+        };
+
+        Ok(GlobalStore {
+            path,
+            saved: values.clone(),
+            values,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the store back if any value changed since it was loaded.
+    pub fn save(&mut self) -> Result<(), RunError> {
+        if self.values == self.saved {
+            return Ok(());
+        }
+
+        let write_err = |e| RunError::FileWrite(self.path.display().to_string(), e);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(write_err)?;
+        }
+        let mut sorted: Vec<(&String, &String)> = self.values.iter().collect();
+        sorted.sort();
+        let map: serde_json::Map<String, Value> = sorted
+            .into_iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        let content = serde_json::to_string_pretty(&map).expect("string map serializes");
+        fs::write(&self.path, content + "\n").map_err(write_err)?;
+
+        self.saved = self.values.clone();
+        Ok(())
+    }
+}
+
+fn parse_store(content: &str) -> Option<Variables> {
+    let Value::Object(map) = serde_json::from_str(content).ok()? else {
+        return None;
+    };
+    map.into_iter()
+        .map(|(k, v)| match v {
+            Value::String(s) => Some((k, s)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_missing_store_is_empty_and_save_round_trips() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let mut store = GlobalStore::load(dir).unwrap();
+        assert!(store.values.is_empty());
+        store.save().unwrap();
+        assert!(!dir.join(STORE_DIR).exists());
+
+        store.values.insert("token".to_string(), "abc".to_string());
+        store.save().unwrap();
+
+        let reloaded = GlobalStore::load(dir).unwrap();
+        assert_eq!(reloaded.values["token"], "abc");
+    }
+
+    #[test]
+    fn workspace_is_nearest_store_then_env_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let nested = root.join("api").join("users");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(workspace_dir(&nested), nested);
+
+        fs::write(root.join(PUBLIC_ENV_FILE), "{}").unwrap();
+        assert_eq!(workspace_dir(&nested), root);
+
+        fs::create_dir_all(root.join("api").join(STORE_DIR)).unwrap();
+        assert_eq!(workspace_dir(&nested), root.join("api"));
+    }
+
+    #[test]
+    fn parse_store_rejects_non_string_values() {
+        assert!(parse_store(r#"{"a": "1"}"#).is_some());
+        assert!(parse_store(r#"{"a": 1}"#).is_none());
+        assert!(parse_store("[]").is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use crate::body::Body;
+use crate::error::RunError;
+use crate::vars::Scope;
+
+/// Extracts the `boundary` parameter of a `multipart/*` content type.
+/// Returns `None` for non-multipart types.
+pub fn boundary(content_type: &str) -> Option<Result<String, RunError>> {
+    let mut params = content_type.split(';');
+    let mime = params.next()?.trim();
+    if !mime.to_ascii_lowercase().starts_with("multipart/") {
+        return None;
+    }
+
+    let boundary = params
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        .filter(|value| !value.is_empty());
+
+    Some(boundary.ok_or_else(|| RunError::Multipart(format!("{} has no boundary parameter", mime))))
+}
+
+/// Assembles a multipart body written as in the JetBrains spec: parts are
+/// delimited by `--boundary` lines and closed by `--boundary--`, and part
+/// content may include files with `< path`. Lines are joined with CRLF and
+/// file parts are streamed as raw bytes.
+pub fn assemble(
+    text: &str,
+    boundary: &str,
+    base_dir: &Path,
+    scope: &Scope,
+) -> Result<Body, RunError> {
+    let delimiter = format!("--{}", boundary);
+    let closing = format!("--{}--", boundary);
+
+    let first = text.lines().map(str::trim).find(|l| !l.is_empty());
+    if first != Some(delimiter.as_str()) {
+        return Err(RunError::Multipart(format!(
+            "body must start with the boundary line {}",
+            delimiter
+        )));
+    }
+    if !text.lines().any(|line| line.trim() == closing) {
+        return Err(RunError::Multipart(format!(
+            "body is missing the closing boundary line {}",
+            closing
+        )));
+    }
+
+    let mut body = Body::assemble_lines(text, b"\r\n", base_dir, scope)?;
+    body.push_bytes(b"\r\n".to_vec());
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Read;
+
+    fn read_all(body: Body) -> Vec<u8> {
+        let mut out = Vec::new();
+        body.into_reader().unwrap().read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn boundary_from_content_type() {
+        let b = boundary("multipart/form-data; boundary=WebAppBoundary");
+        assert_eq!(b.unwrap().unwrap(), "WebAppBoundary");
+
+        let quoted = boundary("Multipart/Form-Data; charset=utf-8; boundary=\"a b\"");
+        assert_eq!(quoted.unwrap().unwrap(), "a b");
+    }
+
+    #[test]
+    fn boundary_ignores_other_types() {
+        assert!(boundary("application/json").is_none());
+    }
+
+    #[test]
+    fn boundary_missing_is_error() {
+        let err = boundary("multipart/form-data").unwrap().unwrap_err();
+        assert!(matches!(err, RunError::Multipart(_)));
+    }
+
+    #[test]
+    fn assemble_frames_parts_with_crlf_and_embeds_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join("image.png"), [0x89u8, b'P', b'N', b'G', 0x00]).unwrap();
+
+        let text = "--XYZ\n\
+                    Content-Disposition: form-data; name=\"title\"\n\
+                    \n\
+                    hello\n\
+                    --XYZ\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"image.png\"\n\
+                    Content-Type: image/png\n\
+                    \n\
+                    < ./image.png\n\
+                    --XYZ--";
+        let body = assemble(text, "XYZ", dir, &Scope::default()).unwrap();
+        let len = body.len();
+
+        let mut expected = b"--XYZ\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\
+            \r\n\
+            hello\r\n\
+            --XYZ\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"image.png\"\r\n\
+            Content-Type: image/png\r\n\
+            \r\n"
+            .to_vec();
+        expected.extend_from_slice(&[0x89, b'P', b'N', b'G', 0x00]);
+        expected.extend_from_slice(b"\r\n--XYZ--\r\n");
+
+        let bytes = read_all(body);
+        assert_eq!(bytes, expected);
+        assert_eq!(len, expected.len() as u64);
+    }
+
+    #[test]
+    fn assemble_requires_declared_boundary() {
+        let err = assemble(
+            "--OTHER\n\nvalue\n--OTHER--",
+            "XYZ",
+            Path::new("."),
+            &Scope::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, RunError::Multipart(_)));
+    }
+
+    #[test]
+    fn assemble_requires_closing_boundary() {
+        let err = assemble("--XYZ\n\nvalue", "XYZ", Path::new("."), &Scope::default()).unwrap_err();
+        assert!(matches!(err, RunError::Multipart(_)));
+    }
+}
@@ -1,5 +1,16 @@
+use std::time::Duration;
+
 use crate::error::RunError;
 
+#[derive(Debug)]
+pub struct Block {
+    /// Zero-based index of the block's first line within the file.
+    pub start: usize,
+    /// Zero-based index one past the block's last line.
+    pub end: usize,
+    pub text: String,
+}
+
 #[derive(Debug)]
 pub struct RequestBlock {
     pub name: Option<String>,
@@ -8,32 +19,311 @@ pub struct RequestBlock {
     pub http_version: Option<String>,
     pub headers: Vec<(String, String)>,
     pub body: Option<String>,
+    pub response_redirect: Option<ResponseRedirect>,
+    pub pre_request_scripts: Vec<Script>,
+    pub response_handlers: Vec<Script>,
+    /// `?? ...` assertion lines, without the marker.
+    pub assertions: Vec<String>,
+    pub options: RequestOptions,
+    pub lines: SourceLines,
+}
+
+/// Behaviour switched by `# @directive` comments in the request block.
+#[derive(Debug, Default, PartialEq)]
+pub struct RequestOptions {
+    /// `# @no-cookie-jar`: neither send stored cookies nor store new ones.
+    pub no_cookie_jar: bool,
+    /// `# @no-redirect`: return 3xx responses instead of following them.
+    pub no_redirect: bool,
+    /// `# @timeout N`: how long to wait for the response to be read.
+    pub timeout: Option<Duration>,
+    /// `# @connection-timeout N`: how long to wait for the connection.
+    pub connection_timeout: Option<Duration>,
+    /// `# @no-log`: leave the exchange out of the output and of everything
+    /// saved between runs.
+    pub no_log: bool,
+}
+
+impl RequestOptions {
+    /// Applies one directive. Unknown directives are ignored so files
+    /// written for other clients still run.
+    fn apply(&mut self, directive: &str, value: &str) -> Result<(), RunError> {
+        match directive {
+            "no-cookie-jar" => self.no_cookie_jar = true,
+            "no-redirect" => self.no_redirect = true,
+            "no-log" => self.no_log = true,
+            "timeout" => self.timeout = Some(parse_timeout(directive, value)?),
+            "connection-timeout" => {
+                self.connection_timeout = Some(parse_timeout(directive, value)?)
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Parses a duration: a number of seconds, or a number followed by `ms`,
+/// `s` or `m`. Zero and negative durations are rejected.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let seconds = match (number.parse::<f64>(), unit.trim()) {
+        (Ok(n), "ms") => Some(n / 1000.0),
+        (Ok(n), "" | "s") => Some(n),
+        (Ok(n), "m") => Some(n * 60.0),
+        _ => None,
+    };
+    seconds
+        .filter(|s| s.is_finite() && *s > 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+fn parse_timeout(directive: &str, value: &str) -> Result<Duration, RunError> {
+    parse_duration(value).ok_or_else(|| {
+        RunError::ParseFailed(format!(
+            "@{} expects a duration such as 30, 500 ms or 2 m, got '{}'",
+            directive,
+            value.trim()
+        ))
+    })
+}
+
+impl RequestBlock {
+    /// The `### Name` of the request, or its request line.
+    pub fn title(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{} {}", self.method, self.url),
+        }
+    }
+}
+
+/// A `{% ... %}` script block or a path to a script file.
+#[derive(Debug, PartialEq)]
+pub enum Script {
+    Inline(String),
+    File(String),
+}
+
+/// A `>> path` (or `>>! path` to overwrite) line saving the response body.
+#[derive(Debug, PartialEq)]
+pub struct ResponseRedirect {
+    pub path: String,
+    pub overwrite: bool,
+}
+
+/// Zero-based line indices, relative to the block, of each parsed element.
+#[derive(Debug, Default)]
+pub struct SourceLines {
+    pub request: usize,
+    pub headers: Vec<usize>,
+    pub body: Vec<usize>,
+    pub redirect: usize,
+    pub assertions: Vec<usize>,
 }
 
-pub fn find_request_block(content: &str, target_line: usize) -> Result<String, RunError> {
+#[derive(Debug, PartialEq)]
+pub struct FileVariable {
+    pub name: String,
+    pub value: String,
+    /// Zero-based line index within the file.
+    pub line: usize,
+}
+
+pub fn split_blocks(content: &str) -> Vec<Block> {
     let lines: Vec<&str> = content.lines().collect();
-    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
     let mut block_start = 0;
 
     for (i, line) in lines.iter().enumerate() {
         if line.trim() == "###" {
             if i > block_start {
-                blocks.push((block_start, i));
+                ranges.push((block_start, i));
             }
             block_start = i + 1;
         }
     }
     if block_start < lines.len() {
-        blocks.push((block_start, lines.len()));
+        ranges.push((block_start, lines.len()));
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| Block {
+            start,
+            end,
+            text: lines[start..end].join("\n"),
+        })
+        .collect()
+}
+
+pub fn find_request_block(content: &str, target_line: usize) -> Result<Block, RunError> {
+    split_blocks(content)
+        .into_iter()
+        .find(|block| target_line > block.start && target_line <= block.end)
+        .ok_or(RunError::NoRequestBlock(target_line))
+}
+
+/// Collects `@name = value` declarations from the preamble of every block,
+/// in file order.
+pub fn file_variables(content: &str) -> Vec<FileVariable> {
+    split_blocks(content)
+        .into_iter()
+        .flat_map(|block| {
+            let (decls, _) = scan_preamble(&block.text);
+            decls
+                .into_iter()
+                .map(move |(idx, name, value)| FileVariable {
+                    name,
+                    value,
+                    line: block.start + idx,
+                })
+        })
+        .collect()
+}
+
+/// Returns the blocks that contain a request, skipping blocks made only of
+/// comments, declarations and scripts.
+pub fn request_blocks(content: &str) -> Vec<Block> {
+    split_blocks(content)
+        .into_iter()
+        .filter(|block| scan_preamble(&block.text).1.is_some())
+        .collect()
+}
+
+/// Scans a block up to its request line, returning the `@name = value`
+/// declarations before it and the request line's index, if there is one.
+fn scan_preamble(text: &str) -> (Vec<(usize, String, String)>, Option<usize>) {
+    let mut decls = Vec::new();
+    let mut in_script = false;
+    for (idx, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if in_script {
+            in_script = !trimmed.contains("%}");
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("//") {
+            continue;
+        }
+        if let Some(script) = trimmed.strip_prefix('<') {
+            in_script = matches!(parse_script(script), ScriptStart::Open(_));
+            continue;
+        }
+        match parse_variable_declaration(trimmed) {
+            Some((name, value)) => decls.push((idx, name, value)),
+            None => return (decls, Some(idx)),
+        }
+    }
+    (decls, None)
+}
+
+/// Finds the block with the given request name, matching exactly first and
+/// then ignoring case. Close names are suggested when nothing matches.
+pub fn find_named_block(content: &str, name: &str) -> Result<Block, RunError> {
+    let named: Vec<(String, Block)> = request_blocks(content)
+        .into_iter()
+        .filter_map(|block| Some((block_name(&block.text)?, block)))
+        .collect();
+
+    let exact = named.iter().position(|(n, _)| n == name);
+    let found = exact.or_else(|| named.iter().position(|(n, _)| n.eq_ignore_ascii_case(name)));
+    if let Some(index) = found {
+        return Ok(named.into_iter().nth(index).expect("index is in range").1);
+    }
+
+    let names: Vec<String> = named.into_iter().map(|(n, _)| n).collect();
+    Err(RunError::UnknownRequestName(
+        name.to_string(),
+        suggest(name, &names),
+    ))
+}
+
+/// Picks up to three names similar to `name`, best first.
+fn suggest(name: &str, names: &[String]) -> Vec<String> {
+    let wanted = name.to_lowercase();
+    let mut scored: Vec<(f64, &String)> = names
+        .iter()
+        .map(|candidate| {
+            let lower = candidate.to_lowercase();
+            let score = if lower.contains(&wanted) || wanted.contains(&lower) {
+                1.0
+            } else {
+                strsim::normalized_levenshtein(&wanted, &lower)
+            };
+            (score, candidate)
+        })
+        .filter(|(score, _)| *score >= 0.5)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// The block's request name, or else its request line as written.
+pub fn block_title(text: &str) -> String {
+    if let Some(name) = block_name(text) {
+        return name;
     }
+    match scan_preamble(text) {
+        (_, Some(idx)) => text.lines().nth(idx).unwrap_or_default().trim().to_string(),
+        (_, None) => String::new(),
+    }
+}
 
-    for (start, end) in &blocks {
-        if target_line > *start && target_line <= *end {
-            return Ok(lines[*start..*end].join("\n"));
+/// The request's name: a `# @name` directive, or else the text of the first
+/// `### Title` comment.
+fn block_name(text: &str) -> Option<String> {
+    let mut title = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(("name", name)) = parse_directive(trimmed) {
+            if !name.is_empty() {
+                return Some(name.to_string());
+            }
+        }
+        if title.is_none() && trimmed.starts_with("###") {
+            let comment = trimmed.trim_start_matches('#').trim();
+            if !comment.is_empty() {
+                title = Some(comment.to_string());
+            }
         }
     }
+    title
+}
 
-    Err(RunError::NoRequestBlock(target_line))
+/// Splits a `# @directive value` (or `// @directive value`) comment into the
+/// directive's name and its possibly empty value.
+fn parse_directive(line: &str) -> Option<(&str, &str)> {
+    let comment = match line.strip_prefix("//") {
+        Some(rest) => rest,
+        None if line.starts_with('#') => line.trim_start_matches('#'),
+        None => return None,
+    };
+    let directive = comment.trim().strip_prefix('@')?;
+    let (name, value) = directive
+        .split_once(char::is_whitespace)
+        .unwrap_or((directive, ""));
+    Some((name, value.trim()))
+}
+
+fn parse_variable_declaration(line: &str) -> Option<(String, String)> {
+    let (name, value) = line.strip_prefix('@')?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((name.to_string(), value.trim().to_string()))
+}
+
+enum ScriptSlot {
+    PreRequest,
+    ResponseHandler,
 }
 
 enum ParseState {
@@ -43,52 +333,121 @@ enum ParseState {
 }
 
 pub fn parse_request(block: &str) -> Result<RequestBlock, RunError> {
-    let mut name: Option<String> = None;
     let mut method = String::new();
     let mut url = String::new();
     let mut http_version: Option<String> = None;
     let mut headers: Vec<(String, String)> = Vec::new();
     let mut body_lines: Vec<&str> = Vec::new();
+    let mut response_redirect: Option<ResponseRedirect> = None;
+    let mut pre_request_scripts: Vec<Script> = Vec::new();
+    let mut response_handlers: Vec<Script> = Vec::new();
+    let mut assertions: Vec<String> = Vec::new();
+    let mut options = RequestOptions::default();
+    let mut open_script: Option<(ScriptSlot, String)> = None;
+    let mut lines = SourceLines::default();
     let mut state = ParseState::Preamble;
 
-    for line in block.lines() {
+    for (idx, line) in block.lines().enumerate() {
         let trimmed = line.trim();
 
+        if let Some((slot, source)) = &mut open_script {
+            match line.find("%}") {
+                Some(end) => {
+                    source.push_str(&line[..end]);
+                    let script = Script::Inline(std::mem::take(source));
+                    match slot {
+                        ScriptSlot::PreRequest => pre_request_scripts.push(script),
+                        ScriptSlot::ResponseHandler => response_handlers.push(script),
+                    }
+                    open_script = None;
+                }
+                None => {
+                    source.push_str(line);
+                    source.push('\n');
+                }
+            }
+            continue;
+        }
+
         if trimmed.is_empty() {
-            if matches!(state, ParseState::Headers) {
-                state = ParseState::Body;
+            match state {
+                ParseState::Headers => state = ParseState::Body,
+                ParseState::Body if !body_lines.is_empty() => {
+                    body_lines.push(line);
+                    lines.body.push(idx);
+                }
+                _ => {}
             }
             continue;
         }
 
         if trimmed.starts_with('#') || trimmed.starts_with("//") {
-            if name.is_none() && trimmed.starts_with("###") {
-                let comment_text = trimmed.trim_start_matches('#').trim();
-                if !comment_text.is_empty() {
-                    name = Some(comment_text.to_string());
-                }
+            if let Some((directive, value)) = parse_directive(trimmed) {
+                options.apply(directive, value)?;
             }
             continue;
         }
 
+        if !matches!(state, ParseState::Preamble) {
+            if let Some(redirect) = parse_response_redirect(trimmed) {
+                response_redirect = Some(redirect);
+                lines.redirect = idx;
+                continue;
+            }
+            if let Some(assertion) = trimmed.strip_prefix("??") {
+                assertions.push(assertion.trim().to_string());
+                lines.assertions.push(idx);
+                continue;
+            }
+            if let Some(handler) = trimmed.strip_prefix('>').filter(|h| !h.starts_with('>')) {
+                match parse_script(handler) {
+                    ScriptStart::Complete(script) => response_handlers.push(script),
+                    ScriptStart::Open(source) => {
+                        open_script = Some((ScriptSlot::ResponseHandler, source))
+                    }
+                }
+                continue;
+            }
+        }
+
         match state {
             ParseState::Body => {
                 body_lines.push(line);
+                lines.body.push(idx);
             }
             ParseState::Preamble => {
-                let parts: Vec<&str> = trimmed.splitn(3, ' ').collect();
-                if parts.len() >= 2 {
-                    method = parts[0].to_string();
-                    url = parts[1].to_string();
-                    if parts.len() == 3 {
-                        http_version = Some(parts[2].to_string());
+                if parse_variable_declaration(trimmed).is_some() {
+                    continue;
+                }
+                if let Some(script) = trimmed.strip_prefix('<') {
+                    match parse_script(script) {
+                        ScriptStart::Complete(script) => pre_request_scripts.push(script),
+                        ScriptStart::Open(source) => {
+                            open_script = Some((ScriptSlot::PreRequest, source))
+                        }
                     }
+                    continue;
+                }
+                if let Some((m, rest)) = trimmed.split_once(' ') {
+                    method = m.to_string();
+                    // The URL may contain spaces inside `{{...}}` placeholders,
+                    // so only a trailing HTTP/x token is taken as the version.
+                    let rest = rest.trim();
+                    match rest.rsplit_once(' ') {
+                        Some((u, ver)) if ver.starts_with("HTTP/") => {
+                            url = u.trim_end().to_string();
+                            http_version = Some(ver.to_string());
+                        }
+                        _ => url = rest.to_string(),
+                    }
+                    lines.request = idx;
                     state = ParseState::Headers;
                 }
             }
             ParseState::Headers => {
                 if let Some((key, value)) = trimmed.split_once(':') {
                     headers.push((key.trim().to_string(), value.trim().to_string()));
+                    lines.headers.push(idx);
                 }
             }
         }
@@ -97,6 +456,16 @@ pub fn parse_request(block: &str) -> Result<RequestBlock, RunError> {
     if method.is_empty() || url.is_empty() {
         return Err(RunError::ParseFailed("no METHOD URL found".to_string()));
     }
+    if open_script.is_some() {
+        return Err(RunError::ParseFailed(
+            "unterminated {% script block".to_string(),
+        ));
+    }
+
+    while body_lines.last().is_some_and(|l| l.trim().is_empty()) {
+        body_lines.pop();
+        lines.body.pop();
+    }
 
     let body = if body_lines.is_empty() {
         None
@@ -105,12 +474,53 @@ pub fn parse_request(block: &str) -> Result<RequestBlock, RunError> {
     };
 
     Ok(RequestBlock {
-        name,
+        name: block_name(block),
         method,
         url,
         http_version,
         headers,
         body,
+        response_redirect,
+        pre_request_scripts,
+        response_handlers,
+        assertions,
+        options,
+        lines,
+    })
+}
+
+enum ScriptStart {
+    Complete(Script),
+    Open(String),
+}
+
+/// Parses what follows a `>` (or `<`) script marker: either an inline
+/// `{% ... %}` block, possibly continuing on later lines, or a file path.
+fn parse_script(rest: &str) -> ScriptStart {
+    let rest = rest.trim();
+    let Some(inline) = rest.strip_prefix("{%") else {
+        return ScriptStart::Complete(Script::File(rest.to_string()));
+    };
+    match inline.find("%}") {
+        Some(end) => ScriptStart::Complete(Script::Inline(inline[..end].to_string())),
+        None if inline.trim().is_empty() => ScriptStart::Open(String::new()),
+        None => ScriptStart::Open(format!("{}\n", inline)),
+    }
+}
+
+fn parse_response_redirect(line: &str) -> Option<ResponseRedirect> {
+    let rest = line.strip_prefix(">>")?;
+    let (rest, overwrite) = match rest.strip_prefix('!') {
+        Some(rest) => (rest, true),
+        None => (rest, false),
+    };
+    let path = rest.trim();
+    if path.is_empty() {
+        return None;
+    }
+    Some(ResponseRedirect {
+        path: path.to_string(),
+        overwrite,
     })
 }
 
@@ -122,7 +532,10 @@ mod tests {
     fn find_request_block_single_block() {
         let content = "GET https://example.com\nAccept: application/json";
         let block = find_request_block(content, 1).unwrap();
-        assert_eq!(block, "GET https://example.com\nAccept: application/json");
+        assert_eq!(
+            block.text,
+            "GET https://example.com\nAccept: application/json"
+        );
     }
 
     #[test]
@@ -130,21 +543,25 @@ mod tests {
         let content =
             "GET https://first.com\n###\nPOST https://second.com\n###\nDELETE https://third.com";
         let block = find_request_block(content, 3).unwrap();
-        assert_eq!(block, "POST https://second.com");
+        assert_eq!(block.start, 2);
+        assert_eq!(block.text, "POST https://second.com");
     }
 
     #[test]
     fn find_request_block_last_block_without_trailing_separator() {
         let content = "GET https://first.com\n###\nPOST https://last.com\nContent-Type: text/plain";
         let block = find_request_block(content, 3).unwrap();
-        assert_eq!(block, "POST https://last.com\nContent-Type: text/plain");
+        assert_eq!(
+            block.text,
+            "POST https://last.com\nContent-Type: text/plain"
+        );
     }
 
     #[test]
     fn find_request_block_with_named_comment() {
         let content = "### My Request\nGET https://example.com";
         let block = find_request_block(content, 1).unwrap();
-        assert_eq!(block, "### My Request\nGET https://example.com");
+        assert_eq!(block.text, "### My Request\nGET https://example.com");
     }
 
     #[test]
@@ -154,6 +571,57 @@ mod tests {
         assert!(matches!(err, RunError::NoRequestBlock(99)));
     }
 
+    #[test]
+    fn split_blocks_returns_every_block() {
+        let content = "GET https://first.com\n###\nPOST https://second.com\nAccept: */*";
+        let blocks = split_blocks(content);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[0].start, blocks[0].end), (0, 1));
+        assert_eq!((blocks[1].start, blocks[1].end), (2, 4));
+        assert_eq!(blocks[1].text, "POST https://second.com\nAccept: */*");
+    }
+
+    #[test]
+    fn file_variables_collected_across_blocks() {
+        let content = "@host = example.com\n@base=https://{{host}}\n\n### First\nGET {{base}}/a\n###\n@id = 7\nGET {{base}}/{{id}}";
+        let vars = file_variables(content);
+        assert_eq!(
+            vars,
+            vec![
+                FileVariable {
+                    name: "host".to_string(),
+                    value: "example.com".to_string(),
+                    line: 0,
+                },
+                FileVariable {
+                    name: "base".to_string(),
+                    value: "https://{{host}}".to_string(),
+                    line: 1,
+                },
+                FileVariable {
+                    name: "id".to_string(),
+                    value: "7".to_string(),
+                    line: 6,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn file_variables_ignores_body_lines() {
+        let content = "POST https://example.com\n\n@notavar = 1";
+        assert!(file_variables(content).is_empty());
+    }
+
+    #[test]
+    fn parse_request_skips_variable_declarations() {
+        let block = "@baseUrl = https://example.com\nGET {{baseUrl}}/posts";
+        let req = parse_request(block).unwrap();
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.url, "{{baseUrl}}/posts");
+        assert_eq!(req.lines.request, 1);
+    }
+
     #[test]
     fn parse_request_basic_get() {
         let block = "GET https://example.com";
@@ -188,6 +656,14 @@ mod tests {
         assert_eq!(req.http_version.as_deref(), Some("HTTP/2"));
     }
 
+    #[test]
+    fn parse_request_url_with_spaced_placeholder() {
+        let block = "GET https://example.com/?n={{$randomInt 1 10}} HTTP/1.1";
+        let req = parse_request(block).unwrap();
+        assert_eq!(req.url, "https://example.com/?n={{$randomInt 1 10}}");
+        assert_eq!(req.http_version.as_deref(), Some("HTTP/1.1"));
+    }
+
     #[test]
     fn parse_request_name_from_comment() {
         let block = "### My Request\nGET https://example.com";
@@ -195,6 +671,74 @@ mod tests {
         assert_eq!(req.name.as_deref(), Some("My Request"));
     }
 
+    #[test]
+    fn parse_request_name_directive_wins_over_title() {
+        let block = "### Log in\n# @name login\nPOST https://example.com/login";
+        assert_eq!(parse_request(block).unwrap().name.as_deref(), Some("login"));
+
+        let block = "// @name   me  \nGET https://example.com/me";
+        assert_eq!(parse_request(block).unwrap().name.as_deref(), Some("me"));
+    }
+
+    #[test]
+    fn parse_request_no_cookie_jar_directive() {
+        let req = parse_request("# @no-cookie-jar\nGET https://example.com").unwrap();
+        assert!(req.options.no_cookie_jar);
+        assert!(req.name.is_none());
+
+        let req = parse_request("// @name plain\nGET https://example.com").unwrap();
+        assert_eq!(req.options, RequestOptions::default());
+    }
+
+    #[test]
+    fn parse_request_directives() {
+        let block = "# @no-redirect\n# @timeout 2 m\n// @connection-timeout 500ms\n# @no-log\n# @some-other-tool x\nGET https://example.com";
+        let options = parse_request(block).unwrap().options;
+        assert_eq!(
+            options,
+            RequestOptions {
+                no_redirect: true,
+                timeout: Some(Duration::from_secs(120)),
+                connection_timeout: Some(Duration::from_millis(500)),
+                no_log: true,
+                ..Default::default()
+            }
+        );
+
+        let options = parse_request("# @timeout 1.5\nGET https://example.com")
+            .unwrap()
+            .options;
+        assert_eq!(options.timeout, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn parse_request_rejects_bad_timeouts() {
+        for value in ["", "soon", "5 h", "0"] {
+            let block = format!("# @timeout {}\nGET https://example.com", value);
+            let err = parse_request(&block).unwrap_err();
+            assert!(matches!(err, RunError::ParseFailed(_)), "{}", value);
+        }
+    }
+
+    #[test]
+    fn find_named_block_matches_exactly_then_ignoring_case() {
+        let content = "### Get a post\nGET https://example.com/posts/1\n###\n# @name create\nPOST https://example.com/posts";
+        assert_eq!(find_named_block(content, "create").unwrap().start, 3);
+        assert_eq!(find_named_block(content, "get A POST").unwrap().start, 0);
+    }
+
+    #[test]
+    fn find_named_block_suggests_close_names() {
+        let content = "### Get a post\nGET https://example.com/posts/1\n###\n### Get all posts\nGET https://example.com/posts\n###\n### Delete user\nDELETE https://example.com/users/1";
+        let err = find_named_block(content, "Get a pots").unwrap_err();
+        assert!(
+            matches!(err, RunError::UnknownRequestName(_, ref s) if s == &["Get a post", "Get all posts"])
+        );
+
+        let err = find_named_block(content, "zzz").unwrap_err();
+        assert!(matches!(err, RunError::UnknownRequestName(_, ref s) if s.is_empty()));
+    }
+
     #[test]
     fn parse_request_multiple_headers() {
         let block = "GET https://example.com\nAccept: application/json\nAuthorization: Bearer token123\nX-Custom: value";
@@ -243,6 +787,132 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_request_records_source_lines() {
+        let block = "### Named\nPOST https://example.com\n// note\nAccept: */*\n\n{\n}";
+        let req = parse_request(block).unwrap();
+        assert_eq!(req.lines.request, 1);
+        assert_eq!(req.lines.headers, vec![3]);
+        assert_eq!(req.lines.body, vec![5, 6]);
+    }
+
+    #[test]
+    fn parse_request_keeps_blank_lines_inside_body() {
+        let block = "POST https://example.com\nContent-Type: text/plain\n\n\nfirst\n\nsecond\n\n";
+        let req = parse_request(block).unwrap();
+        assert_eq!(req.body.as_deref(), Some("first\n\nsecond"));
+        assert_eq!(req.lines.body, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn parse_request_response_redirect() {
+        let block = "POST https://example.com\n\n{\"a\": 1}\n\n>> ./out/response.json";
+        let req = parse_request(block).unwrap();
+        assert_eq!(req.body.as_deref(), Some("{\"a\": 1}"));
+        assert_eq!(
+            req.response_redirect,
+            Some(ResponseRedirect {
+                path: "./out/response.json".to_string(),
+                overwrite: false,
+            })
+        );
+        assert_eq!(req.lines.redirect, 4);
+    }
+
+    #[test]
+    fn parse_request_response_redirect_overwrite_without_body() {
+        let block = "GET https://example.com/export\nAccept: text/csv\n>>! export.csv";
+        let req = parse_request(block).unwrap();
+        assert!(req.body.is_none());
+        assert_eq!(req.headers.len(), 1);
+        assert_eq!(
+            req.response_redirect,
+            Some(ResponseRedirect {
+                path: "export.csv".to_string(),
+                overwrite: true,
+            })
+        );
+    }
+
+    #[test]
+    fn request_blocks_skip_blocks_without_requests() {
+        let content = "@host = example.com\n# shared\n###\nGET https://{{host}}/a\n###\n// nothing here\n###\n< {%\n  request.variables.set(\"x\", 1);\n%}\nPOST https://{{host}}/b";
+        let blocks = request_blocks(content);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].start, 3);
+        assert_eq!(blocks[1].start, 7);
+    }
+
+    #[test]
+    fn parse_request_assertions() {
+        let block = "GET https://example.com/items\nAccept: application/json\n\n?? status == 200\n?? jsonpath $.items.length > 0\n> {% client.log(1) %}";
+        let req = parse_request(block).unwrap();
+        assert!(req.body.is_none());
+        assert_eq!(
+            req.assertions,
+            vec!["status == 200", "jsonpath $.items.length > 0"]
+        );
+        assert_eq!(req.lines.assertions, vec![3, 4]);
+        assert_eq!(req.response_handlers.len(), 1);
+    }
+
+    #[test]
+    fn parse_request_multiline_response_handler() {
+        let block = "POST https://example.com/login\n\n{\"user\": \"a\"}\n\n> {%\n    // store the token\n    client.global.set(\"token\", response.body.token);\n%}";
+        let req = parse_request(block).unwrap();
+        assert_eq!(req.body.as_deref(), Some("{\"user\": \"a\"}"));
+        assert_eq!(
+            req.response_handlers,
+            vec![Script::Inline(
+                "    // store the token\n    client.global.set(\"token\", response.body.token);\n"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_request_inline_and_file_handlers() {
+        let block = "GET https://example.com\n> {% client.test(\"ok\", () => {}); %}\n> ./handler.js\n>> out.json";
+        let req = parse_request(block).unwrap();
+        assert_eq!(
+            req.response_handlers,
+            vec![
+                Script::Inline(" client.test(\"ok\", () => {}); ".to_string()),
+                Script::File("./handler.js".to_string()),
+            ]
+        );
+        assert!(req.response_redirect.is_some());
+    }
+
+    #[test]
+    fn parse_request_pre_request_scripts() {
+        let block = "### Signed\n< {%\n  request.variables.set(\"ts\", Date.now());\n%}\n< ./sign.js\nGET https://example.com?ts={{ts}}";
+        let req = parse_request(block).unwrap();
+        assert_eq!(req.method, "GET");
+        assert_eq!(
+            req.pre_request_scripts,
+            vec![
+                Script::Inline("  request.variables.set(\"ts\", Date.now());\n".to_string()),
+                Script::File("./sign.js".to_string()),
+            ]
+        );
+        assert!(req.response_handlers.is_empty());
+    }
+
+    #[test]
+    fn file_variables_skip_pre_request_scripts() {
+        let content = "< {%\n  const x = 1;\n%}\n@host = example.com\nGET https://{{host}}";
+        let vars = file_variables(content);
+        assert_eq!(vars.len(), 1);
+        assert_eq!(vars[0].name, "host");
+    }
+
+    #[test]
+    fn parse_request_unterminated_handler() {
+        let err = parse_request("GET https://example.com\n> {%\nclient.log(1)").unwrap_err();
+        assert!(matches!(err, RunError::ParseFailed(_)));
+    }
+
     #[test]
     fn parse_request_empty_block() {
         let err = parse_request("").unwrap_err();
@@ -0,0 +1,406 @@
+use yaml_rust2::{YamlEmitter, YamlLoader};
+
+use crate::content::ContentType;
+
+/// A pretty-printer for one kind of content. `format` returns `None` when
+/// the body isn't valid for its type, and the text is shown as received.
+struct Formatter {
+    matches: fn(&ContentType) -> bool,
+    format: fn(&str) -> Option<String>,
+}
+
+/// The formatters, tried in order against the response's content type.
+const FORMATTERS: &[Formatter] = &[
+    Formatter {
+        matches: is_json,
+        format: json,
+    },
+    Formatter {
+        matches: is_html,
+        format: html,
+    },
+    Formatter {
+        matches: is_xml,
+        format: xml,
+    },
+    Formatter {
+        matches: is_form,
+        format: form,
+    },
+    Formatter {
+        matches: is_yaml,
+        format: yaml,
+    },
+];
+
+/// Formats a text body for the terminal with the formatter for its content
+/// type. Bodies without one are still pretty-printed if they are JSON, and
+/// shown as they are otherwise.
+pub fn format_body(text: &str, content_type: &ContentType) -> String {
+    let formatted = match FORMATTERS.iter().find(|f| (f.matches)(content_type)) {
+        Some(formatter) => (formatter.format)(text),
+        None => json(text),
+    };
+    formatted.unwrap_or_else(|| text.to_string())
+}
+
+fn subtype(content_type: &ContentType) -> &str {
+    content_type
+        .mime_type
+        .split_once('/')
+        .map_or("", |(_, subtype)| subtype)
+}
+
+fn is_json(content_type: &ContentType) -> bool {
+    let subtype = subtype(content_type);
+    subtype == "json" || subtype.ends_with("+json")
+}
+
+fn is_html(content_type: &ContentType) -> bool {
+    matches!(
+        content_type.mime_type.as_str(),
+        "text/html" | "application/xhtml+xml"
+    )
+}
+
+fn is_xml(content_type: &ContentType) -> bool {
+    let subtype = subtype(content_type);
+    subtype == "xml" || subtype.ends_with("+xml")
+}
+
+fn is_form(content_type: &ContentType) -> bool {
+    content_type.mime_type == "application/x-www-form-urlencoded"
+}
+
+fn is_yaml(content_type: &ContentType) -> bool {
+    let subtype = subtype(content_type);
+    matches!(subtype, "yaml" | "x-yaml") || subtype.ends_with("+yaml")
+}
+
+fn json(text: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+fn xml(text: &str) -> Option<String> {
+    indent_markup(text, false)
+}
+
+fn html(text: &str) -> Option<String> {
+    indent_markup(text, true)
+}
+
+/// Decodes a form into one `name  value` line per field, with the values
+/// lined up.
+fn form(text: &str) -> Option<String> {
+    let fields: Vec<(String, String)> = url::form_urlencoded::parse(text.trim().as_bytes())
+        .into_owned()
+        .collect();
+    let width = fields.iter().map(|(name, _)| name.chars().count()).max()?;
+    let lines: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("{:<width$}  {}", name, value))
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// Re-emits YAML in block style with consistent indentation, which also
+/// expands `{...}` and `[...]` flow collections. Comments are not kept.
+fn yaml(text: &str) -> Option<String> {
+    let documents = YamlLoader::load_from_str(text).ok()?;
+    let mut out = Vec::new();
+    for document in &documents {
+        let mut emitted = String::new();
+        YamlEmitter::new(&mut emitted).dump(document).ok()?;
+        let body = emitted.strip_prefix("---").unwrap_or(&emitted);
+        out.push(body.trim_start_matches([' ', '\n']).to_string());
+    }
+    Some(out.join("\n---\n"))
+}
+
+/// HTML elements that never have content or a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// HTML elements whose content is not markup and is kept as it is.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "pre", "textarea"];
+
+/// HTML elements whose closing tag is often left out before a sibling.
+const SELF_CLOSING_SIBLINGS: &[&str] = &["li", "p", "option", "tr", "td", "th", "dt", "dd"];
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// An opening tag and the element name.
+    Open(&'a str, &'a str),
+    Close(&'a str, &'a str),
+    /// A tag without content: self-closing and void elements, comments,
+    /// doctypes and processing instructions.
+    Single(&'a str),
+    Text(&'a str),
+    /// Text that must not be reflowed: CDATA sections and the content of
+    /// `RAW_TEXT_ELEMENTS`.
+    Verbatim(&'a str),
+}
+
+/// Splits markup into tags and text. `None` if a tag isn't terminated.
+fn tokenize(source: &str, html: bool) -> Option<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while !rest.is_empty() {
+        let len;
+        if rest.starts_with("<!--") {
+            len = rest.find("-->")? + 3;
+            tokens.push(Token::Single(&rest[..len]));
+        } else if rest.starts_with("<![CDATA[") {
+            len = rest.find("]]>")? + 3;
+            tokens.push(Token::Verbatim(&rest[..len]));
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            len = rest.find('>')? + 1;
+            tokens.push(Token::Single(&rest[..len]));
+        } else if let Some(after) = rest.strip_prefix("</") {
+            len = rest.find('>')? + 1;
+            tokens.push(Token::Close(element_name(after), &rest[..len]));
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            len = tag_end(rest)?;
+            let tag = &rest[..len];
+            let name = element_name(&tag[1..]);
+            let is = |names: &[&str]| html && names.iter().any(|n| n.eq_ignore_ascii_case(name));
+            if tag.ends_with("/>") || is(VOID_ELEMENTS) {
+                tokens.push(Token::Single(tag));
+            } else {
+                tokens.push(Token::Open(name, tag));
+                if is(RAW_TEXT_ELEMENTS) {
+                    let content = &rest[len..];
+                    let end = content
+                        .to_ascii_lowercase()
+                        .find(&format!("</{}", name.to_ascii_lowercase()))?;
+                    tokens.push(Token::Verbatim(&content[..end]));
+                    rest = &content[end..];
+                    continue;
+                }
+            }
+        } else {
+            // A `<` that doesn't start a tag is part of the text.
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            len = rest[first..].find('<').map_or(rest.len(), |i| i + first);
+            tokens.push(Token::Text(&rest[..len]));
+        }
+        rest = &rest[len..];
+    }
+    Some(tokens)
+}
+
+fn element_name(tag: &str) -> &str {
+    let end = tag
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(tag.len());
+    &tag[..end]
+}
+
+/// Length of the tag at the start of `source`, skipping `>` inside quoted
+/// attribute values.
+fn tag_end(source: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in source.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Puts each element on its own line, indented by nesting depth. Elements
+/// holding only a line of text stay on one line. XML that isn't well
+/// formed gives `None`; HTML is taken as it comes.
+fn indent_markup(source: &str, html: bool) -> Option<String> {
+    let tokens = tokenize(source, html)?;
+    let same = |a: &str, b: &str| {
+        if html {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    };
+    let mut lines = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut push = |depth: usize, line: &str| lines.push(format!("{}{}", "  ".repeat(depth), line));
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::Open(name, tag) => {
+                if html
+                    && SELF_CLOSING_SIBLINGS.contains(&name.to_ascii_lowercase().as_str())
+                    && open.last().is_some_and(|last| same(last, name))
+                {
+                    open.pop();
+                }
+                match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (
+                        Some(Token::Text(text) | Token::Verbatim(text)),
+                        Some(Token::Close(n, close)),
+                    ) if same(n, name) && !text.trim().contains('\n') => {
+                        push(open.len(), &format!("{}{}{}", tag, text.trim(), close));
+                        i += 3;
+                        continue;
+                    }
+                    (Some(Token::Close(n, close)), _) if same(n, name) => {
+                        push(open.len(), &format!("{}{}", tag, close));
+                        i += 2;
+                        continue;
+                    }
+                    _ => {
+                        push(open.len(), tag);
+                        open.push(name);
+                    }
+                }
+            }
+            Token::Close(name, tag) => {
+                // HTML closes any elements left open inside this one, and
+                // ignores stray closing tags.
+                match open.iter().rposition(|o| same(o, name)) {
+                    Some(depth) if html || depth + 1 == open.len() => open.truncate(depth),
+                    None if html => {}
+                    _ => return None,
+                }
+                push(open.len(), tag);
+            }
+            Token::Single(tag) => push(open.len(), tag.trim()),
+            Token::Text(text) => {
+                for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    push(open.len(), line);
+                }
+            }
+            Token::Verbatim(text) => {
+                for line in dedent(text) {
+                    push(open.len(), line);
+                }
+            }
+        }
+        i += 1;
+    }
+    if !html && !open.is_empty() {
+        return None;
+    }
+    Some(lines.join("\n"))
+}
+
+/// The non-blank lines of `text`, with the indentation they share removed.
+fn dedent(text: &str) -> Vec<&str> {
+    let lines: Vec<&str> = text
+        .lines()
+        .skip_while(|l| l.trim().is_empty())
+        .map(str::trim_end)
+        .collect();
+    let lines = match lines.iter().rposition(|l| !l.is_empty()) {
+        Some(last) => &lines[..=last],
+        None => return Vec::new(),
+    };
+    let indent = lines
+        .iter()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or(""))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(text: &str, content_type: &str) -> String {
+        format_body(text, &ContentType::parse(content_type))
+    }
+
+    #[test]
+    fn indents_xml() {
+        let soap = r#"<?xml version="1.0"?><soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope"><soap:Body><m:Price a="x>y"><m:Amount>12.5</m:Amount><m:Note/><![CDATA[<raw>]]></m:Price></soap:Body></soap:Envelope>"#;
+        assert_eq!(
+            format(soap, "application/soap+xml; charset=utf-8"),
+            r#"<?xml version="1.0"?>
+<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope">
+  <soap:Body>
+    <m:Price a="x>y">
+      <m:Amount>12.5</m:Amount>
+      <m:Note/>
+      <![CDATA[<raw>]]>
+    </m:Price>
+  </soap:Body>
+</soap:Envelope>"#
+        );
+        // Malformed XML is shown as received.
+        assert_eq!(format("<a><b></a>", "text/xml"), "<a><b></a>");
+    }
+
+    #[test]
+    fn indents_html() {
+        let page = "<!DOCTYPE html><html><head><meta charset=utf-8><script>\n  if (a < b) {\n    go();\n  }\n</script></head><body><ul><li>one<li>two</ul><p>Hi <b>there</b></p></body></html>";
+        assert_eq!(
+            format(page, "text/html"),
+            "<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset=utf-8>
+    <script>
+      if (a < b) {
+        go();
+      }
+    </script>
+  </head>
+  <body>
+    <ul>
+      <li>
+        one
+      <li>
+        two
+    </ul>
+    <p>
+      Hi
+      <b>there</b>
+    </p>
+  </body>
+</html>"
+        );
+    }
+
+    #[test]
+    fn decodes_forms() {
+        assert_eq!(
+            format(
+                "grant_type=password&username=j%40example.com&scope=read+write",
+                "application/x-www-form-urlencoded"
+            ),
+            "grant_type  password\nusername    j@example.com\nscope       read write"
+        );
+    }
+
+    #[test]
+    fn renders_yaml() {
+        assert_eq!(
+            format("name: zhttp\ntags: [http, cli]\n", "application/yaml"),
+            "name: zhttp\ntags:\n  - http\n  - cli"
+        );
+        assert_eq!(format("a: [1", "text/yaml"), "a: [1");
+    }
+
+    #[test]
+    fn falls_back_to_json_or_raw_text() {
+        assert_eq!(
+            format("{\"a\":1}", "application/problem+json"),
+            "{\n  \"a\": 1\n}"
+        );
+        assert_eq!(format("{\"a\":1}", "text/plain"), "{\n  \"a\": 1\n}");
+        assert_eq!(format("<b>hi</b>", "text/plain"), "<b>hi</b>");
+        assert_eq!(format("plain", ""), "plain");
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::RunError;
+use crate::parse::ResponseRedirect;
+
+/// Writes a response body for a `>>` / `>>!` redirect, returning the path
+/// actually written. Without `!`, an existing file is never replaced; a
+/// numeric suffix is added instead.
+pub fn save_body(redirect: &ResponseRedirect, body: &[u8]) -> Result<PathBuf, RunError> {
+    let requested = Path::new(&redirect.path);
+    let path = if redirect.overwrite {
+        requested.to_path_buf()
+    } else {
+        available_path(requested)
+    };
+
+    let write_err = |e| RunError::FileWrite(path.display().to_string(), e);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(write_err)?;
+    }
+    fs::write(&path, body).map_err(write_err)?;
+    Ok(path)
+}
+
+fn available_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded suffix search")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect(path: &Path, overwrite: bool) -> ResponseRedirect {
+        ResponseRedirect {
+            path: path.display().to_string(),
+            overwrite,
+        }
+    }
+
+    #[test]
+    fn save_body_creates_missing_directories() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let target = dir.join("nested").join("out.json");
+        let saved = save_body(&redirect(&target, false), b"{}").unwrap();
+        assert_eq!(saved, target);
+        assert_eq!(fs::read(&target).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn save_body_suffixes_on_conflict() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let target = dir.join("out.json");
+        fs::write(&target, "old").unwrap();
+        fs::write(dir.join("out-1.json"), "older").unwrap();
+
+        let saved = save_body(&redirect(&target, false), b"new").unwrap();
+        assert_eq!(saved, dir.join("out-2.json"));
+        assert_eq!(fs::read(&target).unwrap(), b"old");
+        assert_eq!(fs::read(&saved).unwrap(), b"new");
+    }
+
+    #[test]
+    fn save_body_overwrites_with_bang() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let target = dir.join("out.json");
+        fs::write(&target, "old").unwrap();
+
+        let saved = save_body(&redirect(&target, true), b"new").unwrap();
+        assert_eq!(saved, target);
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+    }
+
+    #[test]
+    fn available_path_without_extension() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let target = dir.join("download");
+        fs::write(&target, "x").unwrap();
+        assert_eq!(available_path(&target), dir.join("download-1"));
+    }
+}
@@ -0,0 +1,528 @@
+use std::fs;
+use std::path::Path;
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rquickjs::{CatchResultExt, Context, Ctx, Function, Runtime};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::error::RunError;
+use crate::exec::Response;
+use crate::parse::{RequestBlock, Script};
+use crate::vars::Variables;
+
+/// Defines `client` on top of the `__input` JSON string set by the host.
+/// The host reads the final state back through `__output()`.
+const CLIENT_PRELUDE: &str = r#"
+const __state = JSON.parse(__input);
+const __tests = [];
+const client = {
+  global: {
+    set(name, value) {
+      __state.globals[name] = typeof value === "string" ? value : JSON.stringify(value);
+    },
+    get(name) {
+      return Object.prototype.hasOwnProperty.call(__state.globals, name)
+        ? __state.globals[name]
+        : null;
+    },
+    isEmpty() {
+      return Object.keys(__state.globals).length === 0;
+    },
+    clear(name) {
+      delete __state.globals[name];
+    },
+    clearAll() {
+      for (const name of Object.keys(__state.globals)) delete __state.globals[name];
+    },
+  },
+  test(name, fn) {
+    try {
+      fn();
+      __tests.push({ name: String(name), passed: true });
+    } catch (e) {
+      const message = e instanceof Error ? e.message : String(e);
+      __tests.push({ name: String(name), passed: false, message });
+    }
+  },
+  assert(condition, message) {
+    if (!condition) throw new Error(message || "Assertion failed");
+  },
+  log(...args) {
+    __print(args.map((a) => (typeof a === "string" ? a : JSON.stringify(a))).join(" "));
+  },
+};
+const crypto = (() => {
+  const builder = (algorithm, keyed) => {
+    let secret = "";
+    let data = "";
+    const self = {
+      withTextSecret(value) {
+        secret = String(value);
+        return self;
+      },
+      updateWithText(value) {
+        data += String(value);
+        return self;
+      },
+      digest() {
+        const run = (encoding) => __digest(algorithm, keyed ? secret : null, data, encoding);
+        return { toHex: () => run("hex"), toBase64: () => run("base64") };
+      },
+    };
+    return self;
+  };
+  return {
+    sha256: () => builder("sha256", false),
+    hmac: { sha256: () => builder("sha256", true) },
+  };
+})();
+function __output() {
+  return { globals: __state.globals, tests: __tests, requestVariables: __state.requestVariables };
+}
+"#;
+
+const REQUEST_PRELUDE: &str = r#"
+const request = (() => {
+  const r = __state.request;
+  const lookup = (map, name) =>
+    Object.prototype.hasOwnProperty.call(map, name) ? map[name] : null;
+  const header = ([name, value]) => ({ name, getRawValue: () => value });
+  return {
+    method: r.method,
+    url: { getRaw: () => r.url },
+    body: { getRaw: () => r.body },
+    headers: {
+      all: () => r.headers.map(header),
+      findByName(name) {
+        const lower = String(name).toLowerCase();
+        const found = r.headers.find(([n]) => n.toLowerCase() === lower);
+        return found ? header(found) : null;
+      },
+    },
+    environment: { get: (name) => lookup(__state.environment, name) },
+    variables: {
+      set(name, value) {
+        __state.requestVariables[name] = typeof value === "string" ? value : JSON.stringify(value);
+      },
+      get: (name) => lookup(__state.requestVariables, name),
+    },
+  };
+})();
+"#;
+
+const RESPONSE_PRELUDE: &str = r#"
+const response = (() => {
+  const r = __state.response;
+  const valuesOf = (name) =>
+    r.headers.filter(([n]) => n.toLowerCase() === String(name).toLowerCase()).map(([, v]) => v);
+  let body = r.body;
+  if (r.contentType.mimeType.includes("json")) {
+    try {
+      body = JSON.parse(r.body);
+    } catch (_) {}
+  }
+  return {
+    status: r.status,
+    body,
+    headers: {
+      valueOf(name) {
+        const values = valuesOf(name);
+        return values.length ? values[0] : null;
+      },
+      valuesOf,
+    },
+    contentType: r.contentType,
+  };
+})();
+"#;
+
+#[derive(Debug, PartialEq)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+/// Runs each response handler in order against `response`. Handlers share
+/// `globals`; every handler gets a fresh engine so top-level declarations
+/// cannot clash.
+pub fn run_response_handlers(
+    handlers: &[Script],
+    response: &Response,
+    globals: &mut Variables,
+) -> Result<Vec<TestResult>, RunError> {
+    let mut tests = Vec::new();
+    for handler in handlers {
+        let (origin, source) = load(handler)?;
+        let input = json!({
+            "globals": globals,
+            "response": response_input(response),
+        });
+        let output = run(&source, &origin, RESPONSE_PRELUDE, input, globals)?;
+        tests.extend(test_results(&output));
+    }
+    Ok(tests)
+}
+
+/// Runs `< {% %}` scripts before the request is resolved and returns the
+/// variables they set with `request.variables.set`. `request` exposes the
+/// raw, unsubstituted request; `request.environment` reads `environment`.
+pub fn run_pre_request_scripts(
+    scripts: &[Script],
+    req: &RequestBlock,
+    environment: &Variables,
+    globals: &mut Variables,
+) -> Result<Variables, RunError> {
+    let mut request_vars = Variables::new();
+    for script in scripts {
+        let (origin, source) = load(script)?;
+        let input = json!({
+            "globals": globals,
+            "environment": environment,
+            "requestVariables": request_vars,
+            "request": {
+                "method": req.method,
+                "url": req.url,
+                "headers": req.headers,
+                "body": req.body,
+            },
+        });
+        let output = run(&source, &origin, REQUEST_PRELUDE, input, globals)?;
+        request_vars = string_map(&output["requestVariables"]);
+    }
+    Ok(request_vars)
+}
+
+fn load(script: &Script) -> Result<(String, String), RunError> {
+    match script {
+        Script::Inline(source) => Ok(("inline script".to_string(), source.clone())),
+        Script::File(path) => {
+            let source = fs::read_to_string(Path::new(path))
+                .map_err(|e| RunError::FileRead(path.clone(), e))?;
+            Ok((path.clone(), source))
+        }
+    }
+}
+
+fn response_input(response: &Response) -> Value {
+    let content_type = response.content_type();
+    json!({
+        "status": response.status,
+        "headers": response.headers,
+        "body": response.text(),
+        "contentType": { "mimeType": content_type.mime_type, "charset": content_type.charset },
+    })
+}
+
+fn digest(
+    algorithm: String,
+    secret: Option<String>,
+    data: String,
+    encoding: String,
+) -> rquickjs::Result<String> {
+    let bytes = match (algorithm.as_str(), secret) {
+        ("sha256", None) => Sha256::digest(data.as_bytes()).to_vec(),
+        ("sha256", Some(secret)) => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                .expect("HMAC accepts keys of any length");
+            mac.update(data.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+        _ => {
+            return Err(rquickjs::Error::new_from_js_message(
+                "string", "digest", algorithm,
+            ))
+        }
+    };
+    Ok(match encoding.as_str() {
+        "base64" => base64::engine::general_purpose::STANDARD.encode(bytes),
+        _ => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+    })
+}
+
+/// Evaluates `source` after the client and phase-specific preludes, copies
+/// the resulting `client.global` values back into `globals` and returns the
+/// script's final state.
+fn run(
+    source: &str,
+    origin: &str,
+    prelude: &str,
+    input: Value,
+    globals: &mut Variables,
+) -> Result<Value, RunError> {
+    let engine_err = |e: rquickjs::Error| RunError::Script(origin.to_string(), e.to_string());
+    let runtime = Runtime::new().map_err(engine_err)?;
+    let context = Context::full(&runtime).map_err(engine_err)?;
+
+    let output = context.with(|ctx| -> Result<String, RunError> {
+        let script_err = |ctx: &Ctx, result: rquickjs::Result<()>| {
+            result
+                .catch(ctx)
+                .map_err(|e| RunError::Script(origin.to_string(), e.to_string().trim().to_string()))
+        };
+
+        let host = ctx.globals();
+        host.set("__input", input.to_string()).map_err(engine_err)?;
+        let print =
+            Function::new(ctx.clone(), |msg: String| println!("{}", msg)).map_err(engine_err)?;
+        host.set("__print", print).map_err(engine_err)?;
+        let digest = Function::new(ctx.clone(), digest).map_err(engine_err)?;
+        host.set("__digest", digest).map_err(engine_err)?;
+
+        script_err(&ctx, ctx.eval(CLIENT_PRELUDE))?;
+        script_err(&ctx, ctx.eval(prelude))?;
+        script_err(&ctx, ctx.eval(source))?;
+
+        ctx.eval::<String, _>("JSON.stringify(__output())")
+            .map_err(engine_err)
+    })?;
+
+    let output: Value = serde_json::from_str(&output)
+        .map_err(|e| RunError::Script(origin.to_string(), e.to_string()))?;
+
+    *globals = string_map(&output["globals"]);
+    Ok(output)
+}
+
+fn string_map(value: &Value) -> Variables {
+    value
+        .as_object()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn test_results(output: &Value) -> Vec<TestResult> {
+    output["tests"]
+        .as_array()
+        .map(|tests| {
+            tests
+                .iter()
+                .map(|t| TestResult {
+                    name: t["name"].as_str().unwrap_or_default().to_string(),
+                    passed: t["passed"].as_bool().unwrap_or(false),
+                    message: t["message"].as_str().map(str::to_string),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn json_response(body: &str) -> Response {
+        Response {
+            status: 200,
+            status_text: "OK".to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            headers: vec![
+                (
+                    "content-type".to_string(),
+                    "application/json; charset=utf-8".to_string(),
+                ),
+                ("set-cookie".to_string(), "a=1".to_string()),
+                ("set-cookie".to_string(), "b=2".to_string()),
+            ],
+            body: body.as_bytes().to_vec(),
+            elapsed: Duration::from_millis(5),
+            wire_size: None,
+            timings: Default::default(),
+            redirects: Vec::new(),
+            proxy: None,
+        }
+    }
+
+    fn inline(source: &str) -> Vec<Script> {
+        vec![Script::Inline(source.to_string())]
+    }
+
+    #[test]
+    fn handler_sets_global_from_json_body() {
+        let mut globals = Variables::new();
+        let handlers = inline(r#"client.global.set("token", response.body.auth.token);"#);
+        run_response_handlers(
+            &handlers,
+            &json_response(r#"{"auth": {"token": "abc"}}"#),
+            &mut globals,
+        )
+        .unwrap();
+        assert_eq!(globals["token"], "abc");
+    }
+
+    #[test]
+    fn handler_reads_existing_globals_and_can_clear() {
+        let mut globals = Variables::new();
+        globals.insert("count".to_string(), "1".to_string());
+        globals.insert("stale".to_string(), "x".to_string());
+        let handlers = inline(
+            r#"client.global.set("count", Number(client.global.get("count")) + 1);
+               client.global.clear("stale");"#,
+        );
+        run_response_handlers(&handlers, &json_response("{}"), &mut globals).unwrap();
+        assert_eq!(globals["count"], "2");
+        assert!(!globals.contains_key("stale"));
+    }
+
+    #[test]
+    fn handler_collects_test_results() {
+        let handlers = inline(
+            r#"client.test("status is 200", () => client.assert(response.status === 200, "bad status"));
+               client.test("has id", () => client.assert(response.body.id === 7, "id missing"));
+               client.test("content type", () => {
+                 client.assert(response.contentType.mimeType === "application/json");
+                 client.assert(response.contentType.charset === "utf-8");
+               });
+               client.test("cookies", () => {
+                 client.assert(response.headers.valueOf("Set-Cookie") === "a=1");
+                 client.assert(response.headers.valuesOf("set-cookie").length === 2);
+               });"#,
+        );
+        let tests =
+            run_response_handlers(&handlers, &json_response("{}"), &mut Variables::new()).unwrap();
+        assert_eq!(
+            tests,
+            vec![
+                TestResult {
+                    name: "status is 200".to_string(),
+                    passed: true,
+                    message: None,
+                },
+                TestResult {
+                    name: "has id".to_string(),
+                    passed: false,
+                    message: Some("id missing".to_string()),
+                },
+                TestResult {
+                    name: "content type".to_string(),
+                    passed: true,
+                    message: None,
+                },
+                TestResult {
+                    name: "cookies".to_string(),
+                    passed: true,
+                    message: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn handlers_run_in_isolated_engines() {
+        let handlers = vec![
+            Script::Inline(r#"const token = "a"; client.global.set("first", token);"#.to_string()),
+            Script::Inline(r#"const token = "b"; client.global.set("second", token);"#.to_string()),
+        ];
+        let mut globals = Variables::new();
+        run_response_handlers(&handlers, &json_response("{}"), &mut globals).unwrap();
+        assert_eq!(globals["first"], "a");
+        assert_eq!(globals["second"], "b");
+    }
+
+    #[test]
+    fn handler_exception_is_script_error() {
+        let handlers = inline(r#"throw new Error("boom");"#);
+        let err = run_response_handlers(&handlers, &json_response("{}"), &mut Variables::new())
+            .unwrap_err();
+        match err {
+            RunError::Script(origin, message) => {
+                assert_eq!(origin, "inline script");
+                assert!(message.contains("boom"), "{}", message);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn handler_syntax_error_is_script_error() {
+        let handlers = inline("client.global.set(");
+        let err = run_response_handlers(&handlers, &json_response("{}"), &mut Variables::new())
+            .unwrap_err();
+        assert!(matches!(err, RunError::Script(_, _)));
+    }
+
+    #[test]
+    fn pre_request_script_sets_request_variables() {
+        let req = crate::parse::parse_request(
+            "POST https://example.com/{{path}}\nX-Key: abc\n\n{\"a\": 1}",
+        )
+        .unwrap();
+        let scripts = inline(
+            r#"request.variables.set("method", request.method);
+               request.variables.set("url", request.url.getRaw());
+               request.variables.set("key", request.headers.findByName("x-key").getRawValue());
+               request.variables.set("body", request.body.getRaw());
+               request.variables.set("secret", request.environment.get("secret"));
+               request.variables.set("n", 42);
+               client.global.set("seen", "yes");"#,
+        );
+        let mut vars = Variables::new();
+        vars.insert("secret".to_string(), "s3cr3t".to_string());
+        let mut globals = Variables::new();
+
+        let request_vars = run_pre_request_scripts(&scripts, &req, &vars, &mut globals).unwrap();
+        assert_eq!(request_vars["method"], "POST");
+        assert_eq!(request_vars["url"], "https://example.com/{{path}}");
+        assert_eq!(request_vars["key"], "abc");
+        assert_eq!(request_vars["body"], "{\"a\": 1}");
+        assert_eq!(request_vars["secret"], "s3cr3t");
+        assert_eq!(request_vars["n"], "42");
+        assert_eq!(globals["seen"], "yes");
+    }
+
+    #[test]
+    fn pre_request_variables_carry_across_scripts() {
+        let req = crate::parse::parse_request("GET https://example.com").unwrap();
+        let scripts = vec![
+            Script::Inline(r#"request.variables.set("a", "1");"#.to_string()),
+            Script::Inline(
+                r#"request.variables.set("b", request.variables.get("a") + "2");"#.to_string(),
+            ),
+        ];
+        let request_vars =
+            run_pre_request_scripts(&scripts, &req, &Variables::new(), &mut Variables::new())
+                .unwrap();
+        assert_eq!(request_vars["b"], "12");
+    }
+
+    #[test]
+    fn crypto_digests_match_known_vectors() {
+        let req = crate::parse::parse_request("GET https://example.com").unwrap();
+        let scripts = inline(
+            r#"request.variables.set("sha", crypto.sha256().updateWithText("abc").digest().toHex());
+               request.variables.set("hmac", crypto.hmac.sha256().withTextSecret("key")
+                 .updateWithText("The quick brown fox jumps over the lazy dog").digest().toHex());
+               request.variables.set("b64", crypto.sha256().updateWithText("").digest().toBase64());"#,
+        );
+        let vars =
+            run_pre_request_scripts(&scripts, &req, &Variables::new(), &mut Variables::new())
+                .unwrap();
+        assert_eq!(
+            vars["sha"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            vars["hmac"],
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_eq!(vars["b64"], "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=");
+    }
+
+    #[test]
+    fn handler_non_json_body_is_string() {
+        let mut response = json_response("plain text");
+        response.headers[0].1 = "text/plain".to_string();
+        let handlers = inline(r#"client.global.set("body", response.body);"#);
+        let mut globals = Variables::new();
+        run_response_handlers(&handlers, &response, &mut globals).unwrap();
+        assert_eq!(globals["body"], "plain text");
+    }
+}
@@ -0,0 +1,356 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use cookie_store::CookieStore;
+
+use crate::assertion;
+use crate::body::Body;
+use crate::config::Config;
+use crate::cookies::CookieJar;
+use crate::dynamic;
+use crate::env;
+use crate::error::RunError;
+use crate::exec::{self, Settings};
+use crate::format;
+use crate::globals::GlobalStore;
+use crate::parse::{self, Block, RequestBlock, Script};
+use crate::save::save_body;
+use crate::script::{self, TestResult};
+use crate::tls;
+use crate::vars::{self, Scope, Variables};
+
+/// The result of running one request block.
+pub struct Outcome {
+    pub status: u16,
+    pub elapsed: Duration,
+    pub results: Vec<TestResult>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed)
+    }
+}
+
+/// A request block together with how running it went.
+pub struct RequestRun {
+    /// One-based line where the block starts.
+    pub line: usize,
+    pub name: String,
+    pub result: Result<Outcome, RunError>,
+}
+
+impl RequestRun {
+    pub fn passed(&self) -> bool {
+        self.result.as_ref().is_ok_and(Outcome::passed)
+    }
+}
+
+/// State shared by the requests of one `.http` file: its variables, the
+/// global store and cookies, which carry over between requests and, through
+/// `.zhttp/cookies.json`, between runs.
+pub struct Session {
+    pub content: String,
+    base_dir: PathBuf,
+    environment: Variables,
+    dotenv: Variables,
+    cli_vars: Vec<(String, String)>,
+    globals: GlobalStore,
+    /// Where cookies are saved, `None` when they only last for this run.
+    cookie_jar: Option<CookieJar>,
+    cookies: CookieStore,
+    settings: Settings,
+    /// Skips printing responses and check results.
+    pub quiet: bool,
+    /// Prints transport details, such as the proxy, with each response.
+    pub verbose: bool,
+}
+
+impl Session {
+    /// Opens `file`, with `overrides` (command-line flags and environment
+    /// variables) layered over the workspace's `.zhttp/config.toml`.
+    pub fn open(
+        file: &str,
+        env_name: Option<&str>,
+        cli_vars: &[(String, String)],
+        overrides: &Config,
+    ) -> Result<Session, RunError> {
+        let content =
+            fs::read_to_string(file).map_err(|e| RunError::FileRead(file.to_string(), e))?;
+        let http_file = Path::new(file);
+        let base_dir = env::http_file_dir(http_file);
+        let cookie_jar = CookieJar::open(&base_dir, env_name);
+        let config = Config::load(&base_dir)?.merge(overrides.clone());
+        let ssl = env::load_ssl_configuration(http_file, env_name)?;
+        let mut settings = config.settings();
+        settings.tls = tls::client_config(config.ca_file.as_deref(), config.insecure, &ssl)?;
+        if config.insecure || !ssl.verify_host_certificate {
+            eprintln!(
+                "{}WARNING: TLS certificate verification is disabled. Responses may come from \
+                 anyone between you and the server.{}",
+                format::RED,
+                format::RESET
+            );
+        }
+
+        Ok(Session {
+            environment: env::load_environment(http_file, env_name)?,
+            dotenv: dynamic::load_dotenv(http_file)?,
+            globals: GlobalStore::load(&base_dir)?,
+            content,
+            base_dir,
+            cli_vars: cli_vars.to_vec(),
+            cookies: cookie_jar.load()?,
+            cookie_jar: Some(cookie_jar),
+            settings,
+            quiet: false,
+            verbose: false,
+        })
+    }
+
+    /// Starts from an empty cookie jar that is never saved, so the run
+    /// neither depends on nor changes `.zhttp/cookies.json`.
+    pub fn fresh_cookies(&mut self) {
+        self.cookies = CookieStore::default();
+        self.cookie_jar = None;
+    }
+
+    /// Builds the variables for the next request, so globals set by earlier
+    /// requests are visible to later ones. In-file variables are declared
+    /// last, so they can use the `request_vars` set by pre-request scripts.
+    fn scope(&self, request_vars: Variables) -> Result<Scope, RunError> {
+        // Precedence, lowest first: environment, in-file, global, --var, then
+        // variables set by pre-request scripts.
+        let mut overrides = self.globals.values.clone();
+        overrides.extend(self.cli_vars.iter().cloned());
+        overrides.extend(request_vars);
+        let mut scope = Scope {
+            vars: self.environment.clone(),
+            dotenv: self.dotenv.clone(),
+        };
+        scope.vars.extend(overrides.clone());
+        vars::declare_file_variables(
+            &parse::file_variables(&self.content),
+            &mut scope,
+            &overrides,
+        )?;
+        Ok(scope)
+    }
+
+    /// Runs one request block: pre-request scripts, substitution, the request
+    /// itself, then assertions and response handlers.
+    pub fn run_block(&mut self, block: &Block) -> Result<Outcome, RunError> {
+        let request = parse::parse_request(&block.text)?;
+        if !request.options.no_log {
+            return self.run_request(request, block);
+        }
+        // Cookies and globals a `# @no-log` request sets are dropped
+        // afterwards, so nothing about it outlives the request.
+        let cookies = self.cookies.clone();
+        let globals = self.globals.values.clone();
+        let result = self.run_request(request, block);
+        self.cookies = cookies;
+        self.globals.values = globals;
+        result
+    }
+
+    fn run_request(
+        &mut self,
+        mut request: RequestBlock,
+        block: &Block,
+    ) -> Result<Outcome, RunError> {
+        let no_log = request.options.no_log;
+        let base_dir = &self.base_dir;
+
+        for script in request
+            .pre_request_scripts
+            .iter_mut()
+            .chain(request.response_handlers.iter_mut())
+        {
+            if let Script::File(path) = script {
+                *path = base_dir.join(&*path).display().to_string();
+            }
+        }
+
+        let mut request_vars = Variables::new();
+        if !request.pre_request_scripts.is_empty() {
+            request_vars = script::run_pre_request_scripts(
+                &request.pre_request_scripts,
+                &request,
+                &self.environment,
+                &mut self.globals.values,
+            )?;
+            if !no_log {
+                self.globals.save()?;
+            }
+        }
+        let scope = self.scope(request_vars)?;
+
+        vars::resolve_request(&mut request, &scope, block.start)?;
+        if let Some(redirect) = &mut request.response_redirect {
+            redirect.path = base_dir.join(&redirect.path).display().to_string();
+        }
+
+        let assertions = assertion::parse_all(&request.assertions)?;
+
+        let body = Body::from_request(&request, base_dir, &scope)?;
+        let response = exec::execute_request(&request, body, &mut self.cookies, &self.settings)?;
+        if let (Some(cookie_jar), false) = (&self.cookie_jar, no_log) {
+            cookie_jar.save(&self.cookies)?;
+        }
+        let saved = match &request.response_redirect {
+            Some(redirect) => Some(save_body(redirect, &response.body)?),
+            None => None,
+        };
+        match (self.quiet, no_log) {
+            (true, _) => {}
+            (false, true) => format::print_unlogged(&response),
+            (false, false) => {
+                format::print_response(&response, &request, saved.as_deref(), self.verbose)
+            }
+        }
+
+        let mut results = assertion::evaluate(&assertions, &response);
+        if !request.response_handlers.is_empty() {
+            results.extend(script::run_response_handlers(
+                &request.response_handlers,
+                &response,
+                &mut self.globals.values,
+            )?);
+            if !no_log {
+                self.globals.save()?;
+            }
+        }
+        if !self.quiet {
+            format::print_test_results(&results);
+        }
+
+        Ok(Outcome {
+            status: response.status,
+            elapsed: response.elapsed,
+            results,
+        })
+    }
+
+    /// Runs every request block of the file in order. Failing requests are
+    /// recorded and do not stop the ones after them.
+    pub fn run_all(&mut self) -> Vec<RequestRun> {
+        let mut runs: Vec<RequestRun> = Vec::new();
+        for block in parse::request_blocks(&self.content) {
+            if !self.quiet && !runs.is_empty() {
+                println!();
+            }
+            let result = self.run_block(&block);
+            if let (false, Err(e)) = (self.quiet, &result) {
+                eprintln!("{}", e);
+            }
+            runs.push(RequestRun {
+                line: block.start + 1,
+                name: parse::block_title(&block.text),
+                result,
+            });
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::PUBLIC_ENV_FILE;
+    use crate::test_server::serve_once;
+
+    #[test]
+    fn relative_file_shares_global_store_with_vars_command() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let project = root.join("api");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(root.join(PUBLIC_ENV_FILE), "{}").unwrap();
+        fs::write(project.join("a.http"), "GET http://example.com/\n").unwrap();
+
+        // The same file, relative to the current directory.
+        let cwd = std::env::current_dir().unwrap();
+        let mut relative: PathBuf = cwd.components().skip(1).map(|_| "..").collect();
+        relative.push(project.join("a.http").strip_prefix("/").unwrap());
+
+        let session =
+            Session::open(relative.to_str().unwrap(), None, &[], &Config::default()).unwrap();
+        // `zhttp vars` loads the store from the current directory.
+        let vars_command = GlobalStore::load(&project).unwrap();
+        assert_eq!(session.globals.path(), vars_command.path());
+        assert_eq!(vars_command.path(), root.join(".zhttp/globals.json"));
+    }
+
+    #[test]
+    fn pre_request_variables_reach_file_variables() {
+        let (url, server) = serve_once(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n");
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join(PUBLIC_ENV_FILE), r#"{"dev": {"token": "env"}}"#).unwrap();
+        let file = dir.join("a.http");
+        fs::write(
+            &file,
+            format!(
+                "@auth = Bearer {{{{token}}}}\n\n\
+                 < {{% request.variables.set(\"token\", \"script-\" + request.environment.get(\"token\")); %}}\n\
+                 GET {}\n\
+                 Authorization: {{{{auth}}}}\n",
+                url
+            ),
+        )
+        .unwrap();
+
+        // `--var token=cli` is in scope, but not in the environment.
+        let cli_vars = [("token".to_string(), "cli".to_string())];
+        let mut session = Session::open(
+            file.to_str().unwrap(),
+            Some("dev"),
+            &cli_vars,
+            &Config::default(),
+        )
+        .unwrap();
+        session.quiet = true;
+        let runs = session.run_all();
+        let request = server.join().unwrap();
+        assert!(runs[0].result.is_ok());
+        assert!(
+            request
+                .to_ascii_lowercase()
+                .contains("authorization: bearer script-env\r\n"),
+            "{}",
+            request
+        );
+    }
+
+    #[test]
+    fn no_log_request_leaves_nothing_behind() {
+        let (url, server) = serve_once(
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: session=secret\r\nContent-Length: 0\r\n\
+              Connection: close\r\n\r\n",
+        );
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join(PUBLIC_ENV_FILE), "{}").unwrap();
+        let file = dir.join("a.http");
+        fs::write(
+            &file,
+            format!(
+                "# @no-log\nGET {}\n\n> {{% client.global.set(\"token\", \"secret\"); %}}\n",
+                url
+            ),
+        )
+        .unwrap();
+
+        let mut session =
+            Session::open(file.to_str().unwrap(), None, &[], &Config::default()).unwrap();
+        session.quiet = true;
+        let runs = session.run_all();
+        server.join().unwrap();
+        assert!(runs[0].result.is_ok());
+        assert!(session.cookies.iter_any().next().is_none());
+        assert!(session.globals.values.is_empty());
+        assert!(!dir.join(".zhttp").exists());
+    }
+}
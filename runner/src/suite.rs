@@ -0,0 +1,373 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::error::RunError;
+use crate::session::{RequestRun, Session};
+
+/// All request runs of one `.http` file, or why the file could not be run.
+pub struct FileRun {
+    pub path: String,
+    pub result: Result<Vec<RequestRun>, RunError>,
+}
+
+impl FileRun {
+    fn runs(&self) -> &[RequestRun] {
+        self.result.as_deref().unwrap_or_default()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.result
+            .as_ref()
+            .is_ok_and(|runs| runs.iter().all(RequestRun::passed))
+    }
+}
+
+/// Expands files, directories (searched recursively for `.http` files) and
+/// glob patterns into a sorted list of files. A pattern matching nothing is
+/// an error, so typos don't silently run zero tests.
+pub fn find_files(patterns: &[String]) -> Result<Vec<PathBuf>, RunError> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let pattern = if Path::new(pattern).is_dir() {
+            format!("{}/**/*.http", pattern.trim_end_matches('/'))
+        } else {
+            pattern.clone()
+        };
+        let paths = glob::glob(&pattern)
+            .map_err(|e| RunError::TestFiles(pattern.clone(), e.to_string()))?;
+        let before = files.len();
+        for path in paths {
+            let path = path.map_err(|e| RunError::TestFiles(pattern.clone(), e.to_string()))?;
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        if files.len() == before {
+            return Err(RunError::TestFiles(pattern, "no files found".to_string()));
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+pub fn run_file(
+    path: &Path,
+    env: Option<&str>,
+    vars: &[(String, String)],
+    config: &Config,
+) -> FileRun {
+    let path = path.display().to_string();
+    let result = Session::open(&path, env, vars, config).map(|mut session| {
+        session.quiet = true;
+        session.fresh_cookies();
+        session.run_all()
+    });
+    FileRun { path, result }
+}
+
+fn seconds(run: &RequestRun) -> f64 {
+    run.result
+        .as_ref()
+        .map_or(0.0, |outcome| outcome.elapsed.as_secs_f64())
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if c.is_control() && !matches!(c, '\n' | '\t') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders a JUnit XML report: one test suite per file and one test case per
+/// request. Failed checks become `<failure>`, requests that could not run
+/// become `<error>`.
+pub fn junit(files: &[FileRun]) -> String {
+    let mut suites = String::new();
+    let (mut total_tests, mut total_failures, mut total_errors, mut total_time) = (0, 0, 0, 0.0);
+
+    for file in files {
+        let name = escape_xml(&file.path);
+        let mut cases = String::new();
+        let (mut failures, mut errors, mut time) = (0, 0, 0.0);
+
+        if let Err(e) = &file.result {
+            errors += 1;
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"0\">\n      <error message=\"{}\"/>\n    </testcase>\n",
+                name,
+                name,
+                escape_xml(&e.to_string())
+            ));
+        }
+        for run in file.runs() {
+            time += seconds(run);
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">\n",
+                escape_xml(&format!("{} {}", run.line, run.name)),
+                name,
+                seconds(run)
+            ));
+            match &run.result {
+                Err(e) => {
+                    errors += 1;
+                    cases.push_str(&format!(
+                        "      <error message=\"{}\"/>\n",
+                        escape_xml(&e.to_string())
+                    ));
+                }
+                Ok(outcome) if !outcome.passed() => {
+                    failures += 1;
+                    let failed: Vec<String> = outcome
+                        .results
+                        .iter()
+                        .filter(|r| !r.passed)
+                        .map(|r| match &r.message {
+                            Some(message) => format!("{}: {}", r.name, message),
+                            None => r.name.clone(),
+                        })
+                        .collect();
+                    cases.push_str(&format!(
+                        "      <failure message=\"{}\">{}</failure>\n",
+                        escape_xml(&failed[0]),
+                        escape_xml(&failed.join("\n"))
+                    ));
+                }
+                Ok(_) => {}
+            }
+            cases.push_str("    </testcase>\n");
+        }
+
+        let tests = file.runs().len() + usize::from(file.result.is_err());
+        suites.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n{}  </testsuite>\n",
+            name, tests, failures, errors, time, cases
+        ));
+        total_tests += tests;
+        total_failures += failures;
+        total_errors += errors;
+        total_time += time;
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"zhttp\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n{}</testsuites>\n",
+        total_tests, total_failures, total_errors, total_time, suites
+    )
+}
+
+/// Renders the machine-readable JSON report.
+pub fn json_report(files: &[FileRun]) -> Value {
+    let requests = |file: &FileRun| -> Vec<Value> {
+        file.runs()
+            .iter()
+            .map(|run| match &run.result {
+                Ok(outcome) => json!({
+                    "name": run.name,
+                    "line": run.line,
+                    "passed": outcome.passed(),
+                    "status": outcome.status,
+                    "durationMs": outcome.elapsed.as_millis() as u64,
+                    "checks": outcome.results.iter().map(|r| json!({
+                        "name": r.name,
+                        "passed": r.passed,
+                        "message": r.message,
+                    })).collect::<Vec<_>>(),
+                }),
+                Err(e) => json!({
+                    "name": run.name,
+                    "line": run.line,
+                    "passed": false,
+                    "error": e.to_string(),
+                }),
+            })
+            .collect()
+    };
+
+    let total: usize = files.iter().map(|f| f.runs().len()).sum();
+    let failed: usize = files
+        .iter()
+        .map(|f| f.runs().iter().filter(|r| !r.passed()).count())
+        .sum();
+    json!({
+        "passed": files.iter().all(FileRun::passed),
+        "summary": {
+            "files": files.len(),
+            "requests": total,
+            "failed": failed,
+        },
+        "files": files.iter().map(|file| {
+            let mut entry = json!({
+                "file": file.path,
+                "passed": file.passed(),
+                "requests": requests(file),
+            });
+            if let Err(e) = &file.result {
+                entry["error"] = Value::String(e.to_string());
+            }
+            entry
+        }).collect::<Vec<_>>(),
+    })
+}
+
+pub fn write_report(path: &Path, content: &str) -> Result<(), RunError> {
+    let write_err = |e| RunError::FileWrite(path.display().to_string(), e);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(write_err)?;
+    }
+    fs::write(path, content).map_err(write_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::TestResult;
+    use crate::session::Outcome;
+    use std::time::Duration;
+
+    fn check(name: &str, passed: bool) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            passed,
+            message: (!passed).then(|| "actual: 500".to_string()),
+        }
+    }
+
+    fn sample() -> Vec<FileRun> {
+        vec![
+            FileRun {
+                path: "api/users.http".to_string(),
+                result: Ok(vec![
+                    RequestRun {
+                        line: 1,
+                        name: "List <users>".to_string(),
+                        result: Ok(Outcome {
+                            status: 200,
+                            elapsed: Duration::from_millis(120),
+                            results: vec![check("status == 200", true)],
+                        }),
+                    },
+                    RequestRun {
+                        line: 5,
+                        name: "Create".to_string(),
+                        result: Ok(Outcome {
+                            status: 500,
+                            elapsed: Duration::from_millis(30),
+                            results: vec![check("status == 201", false)],
+                        }),
+                    },
+                    RequestRun {
+                        line: 9,
+                        name: "Delete".to_string(),
+                        result: Err(RunError::Transport("connection refused".to_string())),
+                    },
+                ]),
+            },
+            FileRun {
+                path: "api/broken.http".to_string(),
+                result: Err(RunError::UnknownEnvironment(
+                    "prod".to_string(),
+                    String::new(),
+                )),
+            },
+        ]
+    }
+
+    #[test]
+    fn junit_reports_failures_and_errors() {
+        let xml = junit(&sample());
+        assert!(xml.contains(
+            "<testsuites name=\"zhttp\" tests=\"4\" failures=\"1\" errors=\"2\" time=\"0.150\">"
+        ));
+        assert!(xml.contains(
+            "<testsuite name=\"api/users.http\" tests=\"3\" failures=\"1\" errors=\"1\""
+        ));
+        assert!(xml.contains("name=\"1 List &lt;users&gt;\""));
+        assert!(xml.contains("<failure message=\"status == 201: actual: 500\">"));
+        assert!(xml.contains("<error message=\"Transport error: connection refused\"/>"));
+        assert!(xml.contains(
+            "<testsuite name=\"api/broken.http\" tests=\"1\" failures=\"0\" errors=\"1\""
+        ));
+    }
+
+    #[test]
+    fn json_report_summarizes_runs() {
+        let report = json_report(&sample());
+        assert_eq!(report["passed"], false);
+        assert_eq!(report["summary"]["requests"], 3);
+        assert_eq!(report["summary"]["failed"], 2);
+        let users = &report["files"][0];
+        assert_eq!(users["requests"][0]["durationMs"], 120);
+        assert_eq!(users["requests"][1]["checks"][0]["message"], "actual: 500");
+        assert_eq!(
+            users["requests"][2]["error"],
+            "Transport error: connection refused"
+        );
+        assert!(report["files"][1]["error"].is_string());
+    }
+
+    #[test]
+    fn find_files_expands_directories_and_globs() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        for name in ["a.http", "nested/b.http", "nested/notes.txt"] {
+            fs::write(dir.join(name), "GET https://example.com").unwrap();
+        }
+        let root = dir.display().to_string();
+
+        let files = find_files(std::slice::from_ref(&root)).unwrap();
+        assert_eq!(files, vec![dir.join("a.http"), dir.join("nested/b.http")]);
+
+        let files =
+            find_files(&[format!("{}/**/b.http", root), format!("{}/a.http", root)]).unwrap();
+        assert_eq!(files, vec![dir.join("a.http"), dir.join("nested/b.http")]);
+
+        let err = find_files(&[format!("{}/*.rest", root)]).unwrap_err();
+        assert!(matches!(err, RunError::TestFiles(..)));
+    }
+
+    #[test]
+    fn run_file_ignores_and_keeps_saved_cookies() {
+        use cookie_store::CookieStore;
+
+        use crate::cookies::CookieJar;
+        use crate::test_server::serve_once;
+
+        let (url, server) = serve_once(
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: fresh=1\r\nContent-Length: 0\r\n\
+              Connection: close\r\n\r\n",
+        );
+        let url: url::Url = url.parse().unwrap();
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut saved = CookieStore::default();
+        saved.parse("session=old", &url).unwrap();
+        CookieJar::open(dir, None).save(&saved).unwrap();
+        let jar_file = dir.join(".zhttp/cookies.json");
+        let before = fs::read_to_string(&jar_file).unwrap();
+        fs::write(dir.join("a.http"), format!("GET {}\n", url)).unwrap();
+
+        let run = run_file(&dir.join("a.http"), None, &[], &Config::default());
+        let request = server.join().unwrap();
+        assert!(run.result.unwrap()[0].result.is_ok());
+        assert!(
+            !request.to_ascii_lowercase().contains("cookie:"),
+            "{}",
+            request
+        );
+        assert_eq!(fs::read_to_string(&jar_file).unwrap(), before);
+    }
+}
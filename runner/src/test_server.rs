@@ -0,0 +1,22 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+/// Serves one HTTP/1.1 request on a local port with `response`. Returns the
+/// server's base URL and a handle that yields the request head it received.
+pub fn serve_once(response: &'static [u8]) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (mut socket, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = socket.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        socket.write_all(response).unwrap();
+        String::from_utf8(request).unwrap()
+    });
+    (url, server)
+}
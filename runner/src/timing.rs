@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+/// How long each phase of the final request took. Phases that didn't
+/// happen, such as TLS for `http://` URLs, are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    /// Following the redirects that led to the final request.
+    pub redirects: Duration,
+    pub dns: Option<Duration>,
+    /// Opening the TCP connection, and the connection to and tunnel through
+    /// a proxy, if any.
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    /// From the connection being ready until the response headers arrived,
+    /// sending the request included.
+    pub wait: Duration,
+    /// Reading the response body.
+    pub download: Duration,
+}
+
+impl Timings {
+    /// The phases in order, with their names, skipping those that didn't
+    /// happen.
+    pub fn phases(&self) -> Vec<(&'static str, Duration)> {
+        let redirects = Some(self.redirects).filter(|d| !d.is_zero());
+        [
+            ("Redirects", redirects),
+            ("DNS", self.dns),
+            ("Connect", self.connect),
+            ("TLS", self.tls),
+            ("Wait", Some(self.wait)),
+            ("Download", Some(self.download)),
+        ]
+        .into_iter()
+        .filter_map(|(name, duration)| Some((name, duration?)))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_skip_what_did_not_happen() {
+        let ms = Duration::from_millis;
+        let timings = Timings {
+            dns: Some(ms(5)),
+            connect: Some(ms(10)),
+            wait: ms(95),
+            ..Timings::default()
+        };
+        assert_eq!(
+            timings.phases(),
+            vec![
+                ("DNS", ms(5)),
+                ("Connect", ms(10)),
+                ("Wait", ms(95)),
+                ("Download", Duration::ZERO)
+            ]
+        );
+
+        let redirected = Timings {
+            redirects: ms(30),
+            ..timings
+        };
+        assert_eq!(redirected.phases()[0], ("Redirects", ms(30)));
+    }
+}
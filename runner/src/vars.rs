@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use crate::dynamic;
+use crate::error::RunError;
+use crate::parse::{FileVariable, RequestBlock};
+
+pub type Variables = HashMap<String, String>;
+
+/// Everything a `{{...}}` placeholder can resolve against.
+#[derive(Debug, Default)]
+pub struct Scope {
+    pub vars: Variables,
+    pub dotenv: Variables,
+}
+
+impl Scope {
+    pub fn lookup(&self, name: &str) -> Option<String> {
+        if name.starts_with('$') {
+            return dynamic::evaluate(name, &self.dotenv);
+        }
+        self.vars.get(name).cloned()
+    }
+}
+
+pub fn parse_assignment(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected NAME=VALUE, got '{}'", arg)),
+    }
+}
+
+/// Replaces every `{{name}}` placeholder in `text`. On failure returns the
+/// name of the first variable that could not be resolved.
+pub fn substitute(text: &str, scope: &Scope) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+
+        let name = rest[open + 2..open + 2 + close].trim();
+        match scope.lookup(name) {
+            Some(value) => out.push_str(&value),
+            None => return Err(name.to_string()),
+        }
+        rest = &rest[open + 2 + close + 2..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Adds in-file `@name = value` declarations to the scope in file order, so
+/// a value may reference any variable declared before it. Names present in
+/// `overrides` keep their existing value.
+pub fn declare_file_variables(
+    decls: &[FileVariable],
+    scope: &mut Scope,
+    overrides: &Variables,
+) -> Result<(), RunError> {
+    for decl in decls {
+        if overrides.contains_key(&decl.name) {
+            continue;
+        }
+        let value = substitute(&decl.value, scope)
+            .map_err(|name| RunError::UnresolvedVariable(name, decl.line + 1))?;
+        scope.vars.insert(decl.name.clone(), value);
+    }
+    Ok(())
+}
+
+pub fn resolve_request(
+    req: &mut RequestBlock,
+    scope: &Scope,
+    block_start: usize,
+) -> Result<(), RunError> {
+    let unresolved =
+        |name: String, idx: usize| RunError::UnresolvedVariable(name, block_start + idx + 1);
+
+    req.url = substitute(&req.url, scope).map_err(|name| unresolved(name, req.lines.request))?;
+
+    for (i, (key, value)) in req.headers.iter_mut().enumerate() {
+        let idx = req.lines.headers[i];
+        *key = substitute(key, scope).map_err(|name| unresolved(name, idx))?;
+        *value = substitute(value, scope).map_err(|name| unresolved(name, idx))?;
+    }
+
+    if let Some(redirect) = &mut req.response_redirect {
+        let idx = req.lines.redirect;
+        redirect.path = substitute(&redirect.path, scope).map_err(|name| unresolved(name, idx))?;
+    }
+
+    for (assertion, idx) in req.assertions.iter_mut().zip(&req.lines.assertions) {
+        *assertion = substitute(assertion, scope).map_err(|name| unresolved(name, *idx))?;
+    }
+
+    if let Some(body) = &req.body {
+        let mut resolved = Vec::with_capacity(req.lines.body.len());
+        for (line, idx) in body.lines().zip(&req.lines.body) {
+            resolved.push(substitute(line, scope).map_err(|name| unresolved(name, *idx))?);
+        }
+        req.body = Some(resolved.join("\n"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{file_variables, parse_request};
+
+    fn vars(pairs: &[(&str, &str)]) -> Variables {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn scope(pairs: &[(&str, &str)]) -> Scope {
+        Scope {
+            vars: vars(pairs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn substitute_replaces_placeholders() {
+        let v = scope(&[("host", "example.com"), ("id", "42")]);
+        assert_eq!(
+            substitute("https://{{host}}/items/{{ id }}", &v).unwrap(),
+            "https://example.com/items/42"
+        );
+    }
+
+    #[test]
+    fn substitute_without_placeholders_is_identity() {
+        assert_eq!(
+            substitute("plain text", &Scope::default()).unwrap(),
+            "plain text"
+        );
+    }
+
+    #[test]
+    fn substitute_leaves_unterminated_braces() {
+        assert_eq!(substitute("{{open", &Scope::default()).unwrap(), "{{open");
+    }
+
+    #[test]
+    fn substitute_reports_unresolved_name() {
+        let err = substitute("{{known}} {{missing}}", &scope(&[("known", "x")])).unwrap_err();
+        assert_eq!(err, "missing");
+    }
+
+    #[test]
+    fn substitute_evaluates_dynamic_variables_per_occurrence() {
+        let out = substitute("{{$uuid}} {{ $uuid }}", &Scope::default()).unwrap();
+        let (a, b) = out.split_once(' ').unwrap();
+        assert_eq!(a.len(), 36);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn substitute_reports_unknown_dynamic_variable() {
+        let err = substitute("{{$processEnv}}", &Scope::default()).unwrap_err();
+        assert_eq!(err, "$processEnv");
+    }
+
+    #[test]
+    fn resolve_request_substitutes_all_parts() {
+        let block = "POST https://{{host}}/items\n{{hdr}}: {{token}}\n\n{\"id\": \"{{id}}\"}";
+        let mut req = parse_request(block).unwrap();
+        let v = scope(&[
+            ("host", "example.com"),
+            ("hdr", "Authorization"),
+            ("token", "Bearer abc"),
+            ("id", "7"),
+        ]);
+        resolve_request(&mut req, &v, 0).unwrap();
+        assert_eq!(req.url, "https://example.com/items");
+        assert_eq!(
+            req.headers,
+            vec![("Authorization".to_string(), "Bearer abc".to_string())]
+        );
+        assert_eq!(req.body.as_deref(), Some("{\"id\": \"7\"}"));
+    }
+
+    #[test]
+    fn resolve_request_reports_file_line() {
+        let block = "GET https://example.com\nAccept: */*\n\n{\n  \"a\": \"{{missing}}\"\n}";
+        let mut req = parse_request(block).unwrap();
+        let err = resolve_request(&mut req, &Scope::default(), 10).unwrap_err();
+        assert!(matches!(err, RunError::UnresolvedVariable(ref name, 15) if name == "missing"));
+    }
+
+    #[test]
+    fn declare_file_variables_resolves_references_in_order() {
+        let decls = file_variables("@host = example.com\n@base = https://{{host}}/{{version}}");
+        let mut v = scope(&[("version", "v2")]);
+        declare_file_variables(&decls, &mut v, &Variables::new()).unwrap();
+        assert_eq!(v.vars["base"], "https://example.com/v2");
+    }
+
+    #[test]
+    fn declare_file_variables_override_environment() {
+        let decls = file_variables("@host = file.example.com");
+        let mut v = scope(&[("host", "env.example.com")]);
+        declare_file_variables(&decls, &mut v, &Variables::new()).unwrap();
+        assert_eq!(v.vars["host"], "file.example.com");
+    }
+
+    #[test]
+    fn declare_file_variables_keep_overrides() {
+        let decls = file_variables("@host = file.example.com");
+        let overrides = vars(&[("host", "cli.example.com")]);
+        let mut v = Scope {
+            vars: overrides.clone(),
+            ..Default::default()
+        };
+        declare_file_variables(&decls, &mut v, &overrides).unwrap();
+        assert_eq!(v.vars["host"], "cli.example.com");
+    }
+
+    #[test]
+    fn declare_file_variables_reports_unresolved_reference() {
+        let decls = file_variables("\n@base = https://{{host}}");
+        let err =
+            declare_file_variables(&decls, &mut Scope::default(), &Variables::new()).unwrap_err();
+        assert!(matches!(err, RunError::UnresolvedVariable(ref name, 2) if name == "host"));
+    }
+
+    #[test]
+    fn parse_assignment_splits_on_first_equals() {
+        assert_eq!(
+            parse_assignment("token=a=b").unwrap(),
+            ("token".to_string(), "a=b".to_string())
+        );
+        assert!(parse_assignment("novalue").is_err());
+    }
+}
@@ -0,0 +1,25 @@
+//! Definitions shared by the zhttp runner and language server.
+
+/// Built-in `{{$name ...}}` variables with their descriptions. The runner
+/// evaluates them every time they are substituted; the LSP offers them as
+/// completions.
+pub const DYNAMIC_VARIABLES: &[(&str, &str)] = &[
+    ("$uuid", "Random UUID v4"),
+    ("$random.uuid", "Random UUID v4"),
+    ("$timestamp", "Current Unix timestamp in seconds"),
+    ("$isoTimestamp", "Current UTC time in ISO-8601 format"),
+    (
+        "$randomInt",
+        "Random integer in [0, 1000), or `$randomInt min max`",
+    ),
+    (
+        "$random.integer",
+        "Random integer in [0, 1000), or `$random.integer min max`",
+    ),
+    ("$random.email", "Random email address"),
+    (
+        "$processEnv",
+        "Process environment variable: `$processEnv NAME`",
+    ),
+    ("$dotenv", "Value from the nearest .env file: `$dotenv KEY`"),
+];